@@ -1,26 +1,41 @@
-use cosmwasm_std::{log, to_binary, Api, CosmosMsg, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, StdResult, Storage, Uint128, WasmMsg, QueryRequest, WasmQuery, StdError};
+use cosmwasm_std::{log, to_binary, Api, CosmosMsg, Decimal, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, StdError, StdResult, Storage, Uint128, QueryRequest, WasmQuery};
 
 use crate::state::{
-    read_config, read_current_n, read_pool_info, rewards_read, rewards_store, store_current_n,
-    store_pool_info, Config, PoolInfo, RewardInfo,
+    read_all_rewards, read_config, rewards_read, rewards_store, store_config, AssetInfo, Config,
+    RewardInfo, RewardPool, UserReward,
 };
 use nebula_protocol::factory::{ClusterExistsResponse, QueryMsg::ClusterExists};
 
-use cw20::Cw20HandleMsg;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-// deposit_reward must be from reward token contract
+// deposit_reward must be sent along with the reward asset: a native coin
+// attached as `sent_funds`, or a CW20 transfer routed through the receive
+// hook. The caller resolves which asset was sent and passes it here.
 pub fn deposit_reward<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
+    asset: AssetInfo,
     rewards_amount: Uint128,
 ) -> HandleResult {
-    let n = read_current_n(&deps.storage)?;
-    let mut pool_info = read_pool_info(&deps.storage, n)?;
-    pool_info.reward_sum += rewards_amount;
-    store_pool_info(&mut deps.storage, n, &pool_info)?;
+    let mut config = read_config(&deps.storage)?;
+
+    let pool = pool_mut(&mut config.reward_pools, &asset);
+    if config.total_penalty.is_zero() {
+        // nobody has staked penalty yet, so there is nothing to index the
+        // reward against -- buffer it until the first penalty is recorded.
+        pool.pending_reward += rewards_amount;
+    } else {
+        pool.reward_per_penalty =
+            pool.reward_per_penalty + Decimal::from_ratio(rewards_amount, config.total_penalty);
+    }
+
+    store_config(&mut deps.storage, &config)?;
+
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
             log("action", "deposit_reward"),
+            log("asset", asset.as_key()),
             log("rewards_amount", rewards_amount.to_string()),
         ],
         data: None,
@@ -33,13 +48,11 @@ pub fn record_penalty<S: Storage, A: Api, Q: Querier>(
     reward_owner: HumanAddr,
     penalty_amount: Uint128,
 ) -> HandleResult {
-    let n = read_current_n(&deps.storage)?;
-
     let cluster = env.message.sender;
-    let cfg = read_config(&deps.storage)?;
+    let mut config = read_config(&deps.storage)?;
 
     let res: ClusterExistsResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-        contract_addr: cfg.owner.clone(),
+        contract_addr: config.owner.clone(),
         msg: to_binary(&ClusterExists {
             contract_addr: cluster,
         })?,
@@ -51,14 +64,28 @@ pub fn record_penalty<S: Storage, A: Api, Q: Querier>(
 
     let reward_owner = deps.api.canonical_address(&reward_owner)?;
     let mut reward_info = rewards_read(&deps.storage, &reward_owner)?;
-    before_share_change(&deps.storage, &mut reward_info)?;
 
-    let mut pool_info = read_pool_info(&deps.storage, n)?;
-    pool_info.penalty_sum += penalty_amount;
+    // settle everything the user has accrued at the current indices before the
+    // penalty weight changes.
+    settle(&config, &mut reward_info)?;
+
+    // adding the first penalty: flush any reward that was buffered while no
+    // penalty existed into each pool's index so it is distributed from here on.
+    if config.total_penalty.is_zero() {
+        for pool in config.reward_pools.iter_mut() {
+            if !pool.pending_reward.is_zero() {
+                pool.reward_per_penalty = pool.reward_per_penalty
+                    + Decimal::from_ratio(pool.pending_reward, penalty_amount);
+                pool.pending_reward = Uint128::zero();
+            }
+        }
+    }
+
     reward_info.penalty += penalty_amount;
+    config.total_penalty += penalty_amount;
 
     rewards_store(&mut deps.storage, &reward_owner, &reward_info)?;
-    store_pool_info(&mut deps.storage, n, &pool_info)?;
+    store_config(&mut deps.storage, &config)?;
 
     Ok(HandleResponse {
         messages: vec![],
@@ -70,66 +97,160 @@ pub fn record_penalty<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-// withdraw all rewards or single reward depending on asset_token
+// withdraw every reward asset with a nonzero accrued balance, emitting one
+// transfer message per asset.
 pub fn withdraw_reward<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
 ) -> HandleResult {
+    let config: Config = read_config(&deps.storage)?;
     let reward_owner = deps.api.canonical_address(&env.message.sender)?;
     let mut reward_info = rewards_read(&deps.storage, &reward_owner)?;
-    before_share_change(&deps.storage, &mut reward_info)?;
+    settle(&config, &mut reward_info)?;
 
-    let amount = reward_info.pending_reward;
-    reward_info.pending_reward = Uint128::zero();
-    rewards_store(&mut deps.storage, &reward_owner, &reward_info)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut withdrawn: Vec<(String, Uint128)> = vec![];
+    for pool in config.reward_pools.iter() {
+        let user = user_reward_mut(&mut reward_info.rewards, &pool.info.as_key());
+        if user.pending_reward.is_zero() {
+            continue;
+        }
+        let amount = user.pending_reward;
+        user.pending_reward = Uint128::zero();
+        messages.push(pool.info.transfer_msg(&env.contract.address, &env.message.sender, amount)?);
+        withdrawn.push((pool.info.as_key(), amount));
+    }
 
-    let config: Config = read_config(&deps.storage)?;
+    rewards_store(&mut deps.storage, &reward_owner, &reward_info)?;
 
     Ok(HandleResponse {
-        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: deps.api.human_address(&config.nebula_token)?,
-            msg: to_binary(&Cw20HandleMsg::Transfer {
-                recipient: env.message.sender,
-                amount,
-            })?,
-            send: vec![],
-        })],
+        messages,
         log: vec![
             log("action", "withdraw"),
-            log("amount", amount.to_string()),
+            log(
+                "withdrawn",
+                withdrawn
+                    .iter()
+                    .map(|(a, amt)| format!("{}:{}", a, amt))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
         ],
         data: None,
     })
 }
 
-pub fn increment_n<S: Storage>(storage: &mut S) -> StdResult<()> {
-    let current_n = read_current_n(storage)?;
+// settle moves everything accrued in each reward pool since the user's
+// snapshot into that pool's `pending_reward` and advances the user's index to
+// the global one. The only rounding loss is the sub-unit dust dropped by
+// flooring a single `penalty * (reward_per_penalty - index)` product per pool.
+fn settle(config: &Config, reward_info: &mut RewardInfo) -> StdResult<()> {
+    for pool in config.reward_pools.iter() {
+        let user = user_reward_mut(&mut reward_info.rewards, &pool.info.as_key());
+        if !reward_info.penalty.is_zero() && user.index < pool.reward_per_penalty {
+            let accrued = reward_info.penalty * (pool.reward_per_penalty - user.index);
+            user.pending_reward += accrued;
+        }
+        user.index = pool.reward_per_penalty;
+    }
+    Ok(())
+}
+
+// locate the pool for `asset`, creating a fresh one the first time a reward is
+// deposited in that asset.
+fn pool_mut<'a>(pools: &'a mut Vec<RewardPool>, asset: &AssetInfo) -> &'a mut RewardPool {
+    if let Some(idx) = pools.iter().position(|p| &p.info == asset) {
+        return &mut pools[idx];
+    }
+    pools.push(RewardPool {
+        info: asset.clone(),
+        reward_per_penalty: Decimal::zero(),
+        pending_reward: Uint128::zero(),
+    });
+    pools.last_mut().unwrap()
+}
 
-    let new_pool = PoolInfo {
-        n: current_n + 1,
-        penalty_sum: Uint128::zero(),
-        reward_sum: Uint128::zero(),
-    };
+// locate the user's snapshot for the pool keyed by `key`, creating a zeroed one
+// on first touch.
+fn user_reward_mut<'a>(rewards: &'a mut Vec<UserReward>, key: &str) -> &'a mut UserReward {
+    if let Some(idx) = rewards.iter().position(|r| r.key == key) {
+        return &mut rewards[idx];
+    }
+    rewards.push(UserReward {
+        key: key.to_string(),
+        index: Decimal::zero(),
+        pending_reward: Uint128::zero(),
+    });
+    rewards.last_mut().unwrap()
+}
 
-    store_current_n(storage, current_n + 1)?;
-    store_pool_info(storage, current_n + 1, &new_pool)?;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardInfoResponse {
+    pub staker: HumanAddr,
+    /// The staker's current penalty weight.
+    pub penalty: Uint128,
+    /// Per reward asset, what a `withdraw_reward` would pay out right now.
+    pub rewards: Vec<(AssetInfo, Uint128)>,
+}
 
-    Ok(())
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardInfoResponseList {
+    pub rewards: Vec<RewardInfoResponse>,
 }
 
-// transform penalty into pending reward
-// the penalty must be from before the current n
-pub fn before_share_change<S: Storage>(storage: &S, reward_info: &mut RewardInfo) -> StdResult<()> {
-    let n = read_current_n(storage)?;
-    if reward_info.penalty != Uint128::zero() && reward_info.n != n {
-        let pool_info = read_pool_info(storage, reward_info.n)?;
-
-        // using integers here .. do we care if the remaining fractions of nebula stay in this contract?
-        reward_info.pending_reward += Uint128(
-            pool_info.reward_sum.u128() * reward_info.penalty.u128() / pool_info.penalty_sum.u128(),
-        );
-        reward_info.penalty = Uint128::zero();
-    }
-    reward_info.n = n;
-    Ok(())
+fn build_response(
+    config: &Config,
+    staker: HumanAddr,
+    mut reward_info: RewardInfo,
+) -> StdResult<RewardInfoResponse> {
+    settle(config, &mut reward_info)?;
+    let rewards = config
+        .reward_pools
+        .iter()
+        .map(|pool| {
+            let amount = reward_info
+                .rewards
+                .iter()
+                .find(|r| r.key == pool.info.as_key())
+                .map(|r| r.pending_reward)
+                .unwrap_or_else(Uint128::zero);
+            (pool.info.clone(), amount)
+        })
+        .collect();
+    Ok(RewardInfoResponse {
+        staker,
+        penalty: reward_info.penalty,
+        rewards,
+    })
+}
+
+pub fn query_reward<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    staker: HumanAddr,
+) -> StdResult<RewardInfoResponse> {
+    let config = read_config(&deps.storage)?;
+    let owner = deps.api.canonical_address(&staker)?;
+    let reward_info = rewards_read(&deps.storage, &owner)?;
+    build_response(&config, staker, reward_info)
+}
+
+pub fn query_all_reward_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<RewardInfoResponseList> {
+    let config = read_config(&deps.storage)?;
+    let start_after = start_after
+        .map(|addr| deps.api.canonical_address(&addr))
+        .transpose()?;
+
+    let rewards = read_all_rewards(&deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(owner, reward_info)| {
+            let staker = deps.api.human_address(&owner)?;
+            build_response(&config, staker, reward_info)
+        })
+        .collect::<StdResult<Vec<RewardInfoResponse>>>()?;
+
+    Ok(RewardInfoResponseList { rewards })
 }
@@ -6,13 +6,20 @@ use cosmwasm_std::{
 use cw20::{Cw20HandleMsg, Cw20ReceiveMsg};
 
 use crate::error;
-use crate::ext_query::{query_cw20_balance, query_cw20_token_supply, query_price};
+use crate::ext_query::{
+    query_balance, query_cw20_balance, query_cw20_token_supply, query_price_feed,
+};
+use crate::msg::{TransactionRecord, TxAction};
 use crate::state::{
-    read_config, read_target, save_config, stage_asset, unstage_asset, PenaltyParams,
+    read_config, read_target, record_transaction, save_config, stage_asset, unstage_asset,
+    PenaltyParams,
 };
 use crate::util::{fpdec_to_int, int_to_fpdec, vec_to_string};
 use crate::{
-    msg::{Cw20HookMsg, HandleMsg},
+    msg::{
+        Asset, AssetInfo, ContractStatus, Cw20HookMsg, HandleMsg, Limiter, LimiterEntry,
+        LimiterParams,
+    },
     state::read_staged_asset,
 };
 use crate::{
@@ -26,13 +33,40 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
+    // The killswitch is enforced per-operation inside the individual handlers
+    // (mint/burn gated by `mint_burn_allowed`, staging/unstaging by
+    // `staging_allowed`) so that `StopMintBurn` still lets users recover funds.
     match msg {
         HandleMsg::Receive(msg) => receive_cw20(deps, env, msg),
         HandleMsg::Mint {
             asset_amounts,
             min_tokens,
         } => try_mint(deps, env, &asset_amounts, &min_tokens),
+        HandleMsg::StageNativeAsset { asset } => try_stage_native_asset(deps, env, asset),
+        HandleMsg::Swap {
+            offer_asset,
+            ask_asset,
+            amount,
+            min_return,
+        } => {
+            // native offer: the amount must be attached to the message
+            if !offer_asset.is_native() {
+                return Err(error::not_native_asset(&offer_asset));
+            }
+            offer_asset.assert_sent_native_token(&env.message.sent_funds, amount)?;
+            let sender = env.message.sender.clone();
+            try_swap(deps, env, &sender, offer_asset, ask_asset, amount, min_return)
+        }
         HandleMsg::UnstageAsset { amount, asset } => try_unstage_asset(deps, env, &asset, &amount),
+        HandleMsg::SetContractStatus { status } => try_set_contract_status(deps, env, status),
+        HandleMsg::MarkAssetForRemoval { asset } => try_mark_asset_for_removal(deps, env, asset),
+        HandleMsg::RegisterLimiter { asset, params } => {
+            try_register_limiter(deps, env, asset, params, false)
+        }
+        HandleMsg::UpdateLimiter { asset, params } => {
+            try_register_limiter(deps, env, asset, params, true)
+        }
+        HandleMsg::DeregisterLimiter { asset } => try_deregister_limiter(deps, env, asset),
         HandleMsg::ResetTarget { target } => try_reset_target(deps, env, &target),
         HandleMsg::_SetBasketToken { basket_token } => {
             try_set_basket_token(deps, env, &basket_token)
@@ -57,6 +91,17 @@ pub fn receive_cw20<S: Storage, A: Api, Q: Querier>(
             Cw20HookMsg::StageAsset {} => {
                 try_receive_stage_asset(deps, env, &sender, &sent_asset, sent_amount)
             }
+            Cw20HookMsg::Swap {
+                ask_asset,
+                min_return,
+            } => {
+                let offer_asset = AssetInfo::Cw20 {
+                    contract_addr: sent_asset,
+                };
+                try_swap(
+                    deps, env, &sender, offer_asset, ask_asset, sent_amount, min_return,
+                )
+            }
         }
     } else {
         Err(error::missing_cw20_msg())
@@ -72,6 +117,9 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
     asset_weights: Option<Vec<u32>>,
 ) -> StdResult<HandleResponse> {
     let cfg = read_config(&deps.storage)?;
+    if !cfg.status.mint_burn_allowed() {
+        return Err(error::contract_paused());
+    }
     let basket_token = cfg
         .basket_token
         .clone()
@@ -97,7 +145,7 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
         .assets
         .iter()
         .map(|asset| {
-            int_to_fpdec(query_cw20_balance(&deps, &asset, &env.contract.address).unwrap())
+            int_to_fpdec(query_asset_balance(&deps, asset, &env.contract.address).unwrap())
         })
         .collect();
 
@@ -106,6 +154,8 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
     let m_div_n = int_to_fpdec(burn_amount) / int_to_fpdec(basket_token_supply);
 
     let mut logs: Vec<LogAttribute> = Vec::new();
+    let mut burn_score: Option<FPDecimal> = None;
+    let mut burn_penalty: Option<FPDecimal> = None;
     let redeem_subtotals: Vec<FPDecimal> = match &asset_weights {
         Some(weights) => {
             // ensure the provided weights has the same dimension as our inventory
@@ -119,11 +169,7 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
                 .iter()
                 .map(|&x| FPDecimal::from(x) / weights_sum)
                 .collect();
-            let prices: Vec<FPDecimal> = cfg
-                .assets
-                .iter()
-                .map(|asset| query_price(&deps, &cfg.oracle, &asset).unwrap())
-                .collect();
+            let prices = query_guarded_prices(&deps, &cfg, env.block.time)?;
             let prod = dot(&inv, &prices) / dot(&r, &prices);
             let b: Vec<FPDecimal> = r.iter().map(|&x| m_div_n * prod * x).collect();
             let neg_b: Vec<FPDecimal> = b.iter().map(|&x| FPDecimal::one().mul(-1) * x).collect();
@@ -139,6 +185,8 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
                 s_neg,
             } = cfg.penalty_params;
             let penalty = compute_penalty(score, a_pos, s_pos, a_neg, s_neg);
+            burn_score = Some(score);
+            burn_penalty = Some(penalty);
             logs.push(log("score", score));
             logs.push(log("penalty", penalty));
             b.iter().map(|&x| penalty * x).collect()
@@ -153,18 +201,42 @@ pub fn try_receive_burn<S: Storage, A: Api, Q: Querier>(
     let transfer_msgs: Vec<CosmosMsg> = redeem_totals
         .iter()
         .zip(cfg.assets.iter())
-        .map(|(amt, asset)| {
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: asset.clone(),
-                msg: to_binary(&Cw20HandleMsg::Transfer {
-                    amount: amt.clone(),
-                    recipient: sender.clone(),
-                })
-                .unwrap(),
-                send: vec![],
-            })
-        })
-        .collect();
+        .map(|(amt, asset)| asset.transfer_msg(&env.contract.address, sender, *amt))
+        .collect::<StdResult<Vec<CosmosMsg>>>()?;
+
+    // throttle composition drift: enforce per-asset limiters against the
+    // post-burn weight vector
+    if !cfg.limiters.is_empty() {
+        let prices = query_guarded_prices(&deps, &cfg, env.block.time)?;
+        let post_inv: Vec<FPDecimal> = inv
+            .iter()
+            .zip(redeem_subtotals.iter())
+            .map(|(&i, &r)| i - r)
+            .collect();
+        enforce_limiters(deps, &post_inv, &prices, env.block.time)?;
+    }
+
+    record_transaction(
+        &mut deps.storage,
+        sender,
+        &TransactionRecord {
+            action: TxAction::Burn,
+            assets: cfg
+                .assets
+                .iter()
+                .cloned()
+                .zip(redeem_totals.iter().cloned())
+                .collect(),
+            basket_tokens: burn_amount,
+            score: burn_score,
+            penalty: burn_penalty,
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+
+    // drop any delisted asset that has now fully drained
+    compact_removed_assets(deps, &env.contract.address)?;
 
     Ok(HandleResponse {
         messages: vec![vec![burn_msg], transfer_msgs].concat(),
@@ -200,17 +272,50 @@ pub fn try_receive_stage_asset<S: Storage, A: Api, Q: Querier>(
     sent_amount: Uint128,
 ) -> StdResult<HandleResponse> {
     let cfg = read_config(&deps.storage)?;
+    if !cfg.status.staging_allowed() {
+        return Err(error::contract_paused());
+    }
     if let None = cfg.basket_token {
         return Err(error::basket_token_not_set());
     }
 
-    // if sent asset is not a component asset of basket, reject
-    if !cfg.assets.iter().any(|asset| asset == sent_asset) {
+    // if sent asset is not a CW20 component asset of basket, reject
+    if !cfg.assets.iter().any(|asset| match asset {
+        AssetInfo::Cw20 { contract_addr } => contract_addr == sent_asset,
+        AssetInfo::Native { .. } => false,
+    }) {
         return Err(error::not_component_asset(sent_asset));
     }
 
+    // reject staging of an asset that is being delisted
+    if cfg.assets_pending_removal.iter().any(|a| match a {
+        AssetInfo::Cw20 { contract_addr } => contract_addr == sent_asset,
+        AssetInfo::Native { .. } => false,
+    }) {
+        return Err(error::asset_pending_removal(sent_asset));
+    }
+
     stage_asset(&mut deps.storage, sender, sent_asset, sent_amount)?;
 
+    record_transaction(
+        &mut deps.storage,
+        sender,
+        &TransactionRecord {
+            action: TxAction::Stage,
+            assets: vec![(
+                AssetInfo::Cw20 {
+                    contract_addr: sent_asset.clone(),
+                },
+                sent_amount,
+            )],
+            basket_tokens: Uint128::zero(),
+            score: None,
+            penalty: None,
+            height: _env.block.height,
+            time: _env.block.time,
+        },
+    )?;
+
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
@@ -223,6 +328,307 @@ pub fn try_receive_stage_asset<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+/// Stages a native coin whose amount is attached to the message as
+/// `sent_funds`; the native analogue of the `StageAsset` Cw20 hook.
+pub fn try_stage_native_asset<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    asset: Asset,
+) -> StdResult<HandleResponse> {
+    let cfg = read_config(&deps.storage)?;
+    if !cfg.status.staging_allowed() {
+        return Err(error::contract_paused());
+    }
+    if let None = cfg.basket_token {
+        return Err(error::basket_token_not_set());
+    }
+
+    let denom = match &asset.info {
+        AssetInfo::Native { denom } => denom.clone(),
+        AssetInfo::Cw20 { .. } => return Err(error::not_native_asset(&asset.info)),
+    };
+
+    // if sent asset is not a component asset of basket, reject
+    if !cfg.assets.iter().any(|a| a == &asset.info) {
+        return Err(error::not_component_asset(&asset.info.as_addr()));
+    }
+
+    // reject staging of an asset that is being delisted
+    if cfg.assets_pending_removal.iter().any(|a| a == &asset.info) {
+        return Err(error::asset_pending_removal(&asset.info.as_addr()));
+    }
+
+    // the declared amount must exactly match the coins attached to the message
+    asset
+        .info
+        .assert_sent_native_token(&env.message.sent_funds, asset.amount)?;
+
+    if asset.amount.is_zero() {
+        return Err(error::missing_native_funds(&denom));
+    }
+
+    let sender = env.message.sender.clone();
+    stage_asset(&mut deps.storage, &sender, &asset.info.as_addr(), asset.amount)?;
+
+    record_transaction(
+        &mut deps.storage,
+        &sender,
+        &TransactionRecord {
+            action: TxAction::Stage,
+            assets: vec![(asset.info.clone(), asset.amount)],
+            basket_tokens: Uint128::zero(),
+            score: None,
+            penalty: None,
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "stage_native_asset"),
+            log("sender", &sender),
+            log("asset", &denom),
+            log("staged_amount", asset.amount),
+        ],
+        data: None,
+    })
+}
+
+/// Governance-only killswitch setter. See [`ContractStatus`] for the effect of
+/// each status on mint/burn and staging.
+pub fn try_set_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    status: ContractStatus,
+) -> StdResult<HandleResponse> {
+    let mut cfg = read_config(&deps.storage)?;
+
+    // check permission
+    if env.message.sender != cfg.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    cfg.status = status;
+    save_config(&mut deps.storage, &cfg)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_contract_status"),
+            log("status", format!("{:?}", status)),
+        ],
+        data: None,
+    })
+}
+
+/// Governance-only: flag a component asset for delisting. The asset's target
+/// weight is forced to zero so the penalty mechanism drains it, and further
+/// staging of it is rejected. The asset is only removed from `cfg.assets` once
+/// its balance hits zero (see [`compact_removed_assets`]), so no user value is
+/// ever stranded.
+pub fn try_mark_asset_for_removal<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    asset: AssetInfo,
+) -> StdResult<HandleResponse> {
+    let mut cfg = read_config(&deps.storage)?;
+
+    // check permission
+    if env.message.sender != cfg.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let idx = cfg
+        .assets
+        .iter()
+        .position(|a| a == &asset)
+        .ok_or_else(|| error::not_component_asset(&asset.as_addr()))?;
+
+    if !cfg.assets_pending_removal.iter().any(|a| a == &asset) {
+        cfg.assets_pending_removal.push(asset.clone());
+    }
+
+    // force its target weight to zero, keeping the vectors aligned
+    let mut target = read_target(&deps.storage)?;
+    target[idx] = 0;
+    save_target(&mut deps.storage, &target)?;
+    save_config(&mut deps.storage, &cfg)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "mark_asset_for_removal"),
+            log("asset", asset.as_key()),
+        ],
+        data: None,
+    })
+}
+
+/// Removes any asset flagged for delisting whose basket balance has drained to
+/// zero, dropping it from both `cfg.assets` and the aligned target vector and
+/// persisting the compacted config.
+fn compact_removed_assets<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    contract: &HumanAddr,
+) -> StdResult<()> {
+    let mut cfg = read_config(&deps.storage)?;
+    if cfg.assets_pending_removal.is_empty() {
+        return Ok(());
+    }
+
+    let mut target = read_target(&deps.storage)?;
+    let mut changed = false;
+
+    let pending = cfg.assets_pending_removal.clone();
+    for asset in pending {
+        let balance = query_asset_balance(deps, &asset, contract)?;
+        if !balance.is_zero() {
+            continue;
+        }
+        if let Some(idx) = cfg.assets.iter().position(|a| a == &asset) {
+            cfg.assets.remove(idx);
+            target.remove(idx);
+        }
+        cfg.assets_pending_removal.retain(|a| a != &asset);
+        // a removed asset must never leave a stale limiter behind to block
+        // future ops
+        cfg.limiters.retain(|l| l.asset != asset);
+        changed = true;
+    }
+
+    if changed {
+        save_target(&mut deps.storage, &target)?;
+        save_config(&mut deps.storage, &cfg)?;
+    }
+    Ok(())
+}
+
+/// Governance-only: register a new limiter or, when `update_only` is set,
+/// replace an existing limiter's parameters while keeping its observations.
+pub fn try_register_limiter<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    asset: AssetInfo,
+    params: LimiterParams,
+    update_only: bool,
+) -> StdResult<HandleResponse> {
+    let mut cfg = read_config(&deps.storage)?;
+
+    if env.message.sender != cfg.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    if !cfg.assets.iter().any(|a| a == &asset) {
+        return Err(error::not_component_asset(&asset.as_addr()));
+    }
+
+    let action = match cfg.limiters.iter_mut().find(|l| l.asset == asset) {
+        Some(entry) => {
+            entry.limiter.window = params.window;
+            entry.limiter.divisions = params.divisions;
+            entry.limiter.max_deviation = params.max_deviation;
+            entry.limiter.upper_boundary = params.upper_boundary;
+            "update_limiter"
+        }
+        None => {
+            if update_only {
+                return Err(error::limiter_not_found(&asset.as_addr()));
+            }
+            cfg.limiters.push(LimiterEntry {
+                asset: asset.clone(),
+                limiter: Limiter {
+                    window: params.window,
+                    divisions: params.divisions,
+                    max_deviation: params.max_deviation,
+                    upper_boundary: params.upper_boundary,
+                    observations: vec![],
+                },
+            });
+            "register_limiter"
+        }
+    };
+
+    save_config(&mut deps.storage, &cfg)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", action), log("asset", asset.as_key())],
+        data: None,
+    })
+}
+
+/// Governance-only: remove a limiter.
+pub fn try_deregister_limiter<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    asset: AssetInfo,
+) -> StdResult<HandleResponse> {
+    let mut cfg = read_config(&deps.storage)?;
+
+    if env.message.sender != cfg.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let before = cfg.limiters.len();
+    cfg.limiters.retain(|l| l.asset != asset);
+    if cfg.limiters.len() == before {
+        return Err(error::limiter_not_found(&asset.as_addr()));
+    }
+    save_config(&mut deps.storage, &cfg)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "deregister_limiter"),
+            log("asset", asset.as_key()),
+        ],
+        data: None,
+    })
+}
+
+/// Checks the post-operation weight of every limited asset against its limiter,
+/// rejecting the operation if any exceeds its bound, and otherwise records the
+/// observed weight and persists the advanced limiters.
+fn enforce_limiters<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    post_inv: &[FPDecimal],
+    prices: &[FPDecimal],
+    now: u64,
+) -> StdResult<()> {
+    let mut cfg = read_config(&deps.storage)?;
+    if cfg.limiters.is_empty() {
+        return Ok(());
+    }
+
+    let total = dot(post_inv, prices);
+    if !(total > FPDecimal::zero()) {
+        return Ok(());
+    }
+
+    // weight of each component keyed by its stable asset key
+    let assets = cfg.assets.clone();
+    let weights: Vec<(String, FPDecimal)> = assets
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (a.as_key(), post_inv[i] * prices[i] / total))
+        .collect();
+
+    for entry in cfg.limiters.iter_mut() {
+        let key = entry.asset.as_key();
+        if let Some((_, weight)) = weights.iter().find(|(k, _)| k == &key) {
+            if !entry.limiter.permits(*weight, now) {
+                return Err(error::composition_limit_exceeded(&entry.asset.as_addr()));
+            }
+            entry.limiter.record(*weight, now);
+        }
+    }
+
+    save_config(&mut deps.storage, &cfg)?;
+    Ok(())
+}
+
 /// May be called by the Basket contract owner to reset the target
 pub fn try_reset_target<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -297,6 +703,9 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
     min_tokens: &Option<Uint128>,
 ) -> StdResult<HandleResponse> {
     let cfg = read_config(&deps.storage)?;
+    if !cfg.status.mint_burn_allowed() {
+        return Err(error::contract_paused());
+    }
     let target = read_target(&deps.storage)?;
     let basket_token = cfg
         .basket_token
@@ -305,17 +714,17 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
 
     // ensure that all tokens in asset_amounts have been staged beforehand
     for (asset, amount) in cfg.assets.iter().zip(asset_amounts) {
-        let staged = read_staged_asset(&deps.storage, &env.message.sender, asset).unwrap();
-        println!("asset {} amount {} staged {}", asset, amount, staged);
+        let key = asset.as_addr();
+        let staged = read_staged_asset(&deps.storage, &env.message.sender, &key).unwrap();
         if *amount > staged {
             return Err(error::insufficient_staged(
                 &env.message.sender,
-                asset,
+                &key,
                 *amount,
                 staged,
             ));
         }
-        unstage_asset(&mut deps.storage, &env.message.sender, &asset, *amount)?;
+        unstage_asset(&mut deps.storage, &env.message.sender, &key, *amount)?;
     }
     let c = asset_amounts.iter().map(|&x| int_to_fpdec(x)).collect();
 
@@ -324,16 +733,13 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
         .assets
         .iter()
         .map(|asset| {
-            int_to_fpdec(query_cw20_balance(&deps, &asset, &env.contract.address).unwrap())
+            int_to_fpdec(query_asset_balance(&deps, asset, &env.contract.address).unwrap())
         })
         .collect();
 
-    // get current prices of each token via oracle
-    let prices: Vec<FPDecimal> = cfg
-        .assets
-        .iter()
-        .map(|asset| query_price(&deps, &cfg.oracle, &asset).unwrap())
-        .collect();
+    // get current prices of each token via oracle, rejecting stale or
+    // low-confidence feeds
+    let prices = query_guarded_prices(&deps, &cfg, env.block.time)?;
 
     // compute penalty
     let score = compute_score(&inv, &c, &prices, &target);
@@ -358,6 +764,11 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
         }
     }
 
+    // throttle composition drift: enforce per-asset limiters against the
+    // post-mint weight vector
+    let post_inv: Vec<FPDecimal> = inv.iter().zip(c.iter()).map(|(&i, &a)| i + a).collect();
+    enforce_limiters(deps, &post_inv, &prices, env.block.time)?;
+
     let mint_msg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: basket_token.clone(),
         msg: to_binary(&Cw20HandleMsg::Mint {
@@ -367,6 +778,25 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
         send: vec![],
     });
 
+    record_transaction(
+        &mut deps.storage,
+        &env.message.sender,
+        &TransactionRecord {
+            action: TxAction::Mint,
+            assets: cfg
+                .assets
+                .iter()
+                .cloned()
+                .zip(asset_amounts.iter().cloned())
+                .collect(),
+            basket_tokens: mint_total,
+            score: Some(score),
+            penalty: Some(penalty),
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+
     // mint and send number of tokens to user
     Ok(HandleResponse {
         messages: vec![mint_msg],
@@ -382,26 +812,158 @@ pub fn try_mint<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+/// Swap `offer_amount` of `offer_asset` for `ask_asset` directly inside the
+/// basket. The offer has already been received (native coins via `sent_funds`,
+/// tokens via the Cw20 transfer that triggered the hook), so it is reflected in
+/// the queried inventory. We score the net change `(+offer, -ask)` against the
+/// target and run it through `compute_penalty`, so a swap that pushes the
+/// basket toward target is rewarded while one that worsens balance pays a fee.
+pub fn try_swap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    sender: &HumanAddr,
+    offer_asset: AssetInfo,
+    ask_asset: AssetInfo,
+    offer_amount: Uint128,
+    min_return: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let cfg = read_config(&deps.storage)?;
+    if !cfg.status.mint_burn_allowed() {
+        return Err(error::contract_paused());
+    }
+    if offer_asset == ask_asset {
+        return Err(error::same_swap_asset(&offer_asset.as_addr()));
+    }
+
+    let offer_idx = cfg
+        .assets
+        .iter()
+        .position(|a| a == &offer_asset)
+        .ok_or_else(|| error::not_component_asset(&offer_asset.as_addr()))?;
+    let ask_idx = cfg
+        .assets
+        .iter()
+        .position(|a| a == &ask_asset)
+        .ok_or_else(|| error::not_component_asset(&ask_asset.as_addr()))?;
+
+    let target = read_target(&deps.storage)?;
+
+    // current inventory already includes the received offer
+    let cur_inv: Vec<FPDecimal> = cfg
+        .assets
+        .iter()
+        .map(|asset| int_to_fpdec(query_asset_balance(&deps, asset, &env.contract.address).unwrap()))
+        .collect();
+    let prices = query_guarded_prices(&deps, &cfg, env.block.time)?;
+
+    let offer_fp = int_to_fpdec(offer_amount);
+    let offer_value = offer_fp * prices[offer_idx];
+    // pre-penalty ask amount at mid price
+    let ask_pre = offer_value / prices[ask_idx];
+
+    // inventory as it stood before the offer arrived
+    let mut inv_before = cur_inv.clone();
+    inv_before[offer_idx] = inv_before[offer_idx] - offer_fp;
+
+    // proposed net change: add the offer, remove the ask
+    let mut c = vec![FPDecimal::zero(); cfg.assets.len()];
+    c[offer_idx] = offer_fp;
+    c[ask_idx] = FPDecimal::one().mul(-1) * ask_pre;
+
+    let score = compute_score(&inv_before, &c, &prices, &target);
+    let PenaltyParams {
+        a_pos,
+        s_pos,
+        a_neg,
+        s_neg,
+    } = cfg.penalty_params;
+    let penalty = compute_penalty(score, a_pos, s_pos, a_neg, s_neg);
+
+    // penalty rewards (>1) or taxes (<1) the realised return, but we can never
+    // pay out more of the ask asset than the basket currently holds
+    let ask_avail = cur_inv[ask_idx];
+    let ask_out = {
+        let proposed = penalty * ask_pre;
+        if proposed > ask_avail {
+            ask_avail
+        } else {
+            proposed
+        }
+    };
+    let (return_amount, return_roundoff) = fpdec_to_int(ask_out);
+
+    if let Some(min) = min_return {
+        if return_amount < min {
+            return Err(error::below_min_return(return_amount, min));
+        }
+    }
+
+    // throttle composition drift: enforce per-asset limiters against the
+    // post-swap inventory
+    let mut post_inv = cur_inv.clone();
+    post_inv[ask_idx] = post_inv[ask_idx] - ask_out;
+    enforce_limiters(deps, &post_inv, &prices, env.block.time)?;
+
+    let messages = vec![ask_asset.transfer_msg(&env.contract.address, sender, return_amount)?];
+
+    record_transaction(
+        &mut deps.storage,
+        sender,
+        &TransactionRecord {
+            action: TxAction::Swap,
+            assets: vec![
+                (offer_asset.clone(), offer_amount),
+                (ask_asset.clone(), return_amount),
+            ],
+            basket_tokens: Uint128::zero(),
+            score: Some(score),
+            penalty: Some(penalty),
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "swap"),
+            log("sender", sender),
+            log("offer_asset", offer_asset.as_key()),
+            log("ask_asset", ask_asset.as_key()),
+            log("offer_amount", offer_amount),
+            log("return_amount", return_amount),
+            log("return_roundoff", return_roundoff),
+            log("score", score),
+            log("penalty", penalty),
+        ],
+        data: None,
+    })
+}
+
 pub fn try_unstage_asset<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    asset: &HumanAddr,
+    asset: &AssetInfo,
     amount: &Option<Uint128>,
 ) -> StdResult<HandleResponse> {
     let cfg = read_config(&deps.storage)?;
+    if !cfg.status.staging_allowed() {
+        return Err(error::contract_paused());
+    }
 
     // if sent asset is not a component asset of basket, reject
-    if !cfg.assets.iter().any(|x| asset == x) {
-        return Err(error::not_component_asset(asset));
+    if !cfg.assets.iter().any(|x| x == asset) {
+        return Err(error::not_component_asset(&asset.as_addr()));
     }
 
-    let curr_staged = read_staged_asset(&deps.storage, &env.message.sender, asset)?;
+    let key = asset.as_addr();
+    let curr_staged = read_staged_asset(&deps.storage, &env.message.sender, &key)?;
     let to_unstage = match amount {
         Some(amt) => {
             if *amt > curr_staged {
                 return Err(error::insufficient_staged(
                     &env.message.sender,
-                    asset,
+                    &key,
                     *amt,
                     curr_staged,
                 ));
@@ -411,18 +973,25 @@ pub fn try_unstage_asset<S: Storage, A: Api, Q: Querier>(
         None => curr_staged,
     };
 
-    unstage_asset(&mut deps.storage, &env.message.sender, asset, to_unstage)?;
+    unstage_asset(&mut deps.storage, &env.message.sender, &key, to_unstage)?;
 
-    // return asset
+    record_transaction(
+        &mut deps.storage,
+        &env.message.sender,
+        &TransactionRecord {
+            action: TxAction::Unstage,
+            assets: vec![(asset.clone(), to_unstage)],
+            basket_tokens: Uint128::zero(),
+            score: None,
+            penalty: None,
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+
+    // return asset (native coins via BankMsg::Send, tokens via Cw20 Transfer)
     let messages = if !to_unstage.is_zero() {
-        vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: asset.clone(),
-            msg: to_binary(&Cw20HandleMsg::Transfer {
-                amount: to_unstage.clone(),
-                recipient: env.message.sender.clone(),
-            })?,
-            send: vec![],
-        })]
+        vec![asset.transfer_msg(&env.contract.address, &env.message.sender, to_unstage)?]
     } else {
         vec![]
     };
@@ -431,13 +1000,57 @@ pub fn try_unstage_asset<S: Storage, A: Api, Q: Querier>(
         messages,
         log: vec![
             log("action", "unstage_asset"),
-            log("asset", asset),
+            log("asset", asset.as_key()),
             log("amount", to_unstage),
         ],
         data: None,
     })
 }
 
+/// Reads every constituent's price from the oracle and rejects the operation
+/// if any required feed is stale or too uncertain. A feed older than
+/// `max_price_age_secs` fails the staleness check; when `max_confidence_ratio`
+/// is set, a feed whose `confidence / price` exceeds it fails the confidence
+/// check. This guards depositors from trading against degraded data.
+fn query_guarded_prices<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cfg: &crate::state::BasketConfig,
+    now: u64,
+) -> StdResult<Vec<FPDecimal>> {
+    cfg.assets
+        .iter()
+        .map(|asset| {
+            let feed = query_price_feed(deps, &cfg.oracle, &asset.as_addr())?;
+
+            let age = now.saturating_sub(feed.last_updated_time);
+            if age > cfg.max_price_age_secs {
+                return Err(error::stale_price(&asset.as_addr(), age, cfg.max_price_age_secs));
+            }
+
+            if let Some(max_ratio) = cfg.max_confidence_ratio {
+                if feed.price > FPDecimal::zero() && feed.confidence / feed.price > max_ratio {
+                    return Err(error::excessive_confidence(&asset.as_addr()));
+                }
+            }
+
+            Ok(feed.price)
+        })
+        .collect()
+}
+
+/// Reads the basket's on-hand balance of a constituent, dispatching to the
+/// native bank query or the CW20 balance query as appropriate.
+fn query_asset_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    asset: &AssetInfo,
+    account: &HumanAddr,
+) -> StdResult<Uint128> {
+    match asset {
+        AssetInfo::Cw20 { contract_addr } => query_cw20_balance(deps, contract_addr, account),
+        AssetInfo::Native { denom } => query_balance(deps, account, denom.clone()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
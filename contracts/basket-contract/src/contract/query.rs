@@ -1,8 +1,11 @@
 use cosmwasm_std::{to_binary, Api, Binary, Extern, HumanAddr, Querier, StdResult, Storage};
 
-use crate::state::{read_config, read_target};
+use crate::state::{read_config, read_target, read_transactions};
 use crate::{
-    msg::{ConfigResponse, QueryMsg, StagedAmountResponse, TargetResponse},
+    msg::{
+        AssetInfo, ConfigResponse, LimitersResponse, QueryMsg, StagedAmountResponse,
+        TargetResponse, TransactionHistoryResponse,
+    },
     test_helper::read_staged_asset,
 };
 
@@ -16,9 +19,34 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
         QueryMsg::StagedAmount { account, asset } => {
             to_binary(&query_staged_amount(deps, &account, &asset)?)
         }
+        QueryMsg::Limiters {} => to_binary(&query_limiters(deps)?),
+        QueryMsg::TransactionHistory {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_transaction_history(deps, &address, start_after, limit)?),
     }
 }
 
+fn query_transaction_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TransactionHistoryResponse> {
+    let history = read_transactions(&deps.storage, address, start_after, limit)?;
+    Ok(TransactionHistoryResponse { history })
+}
+
+fn query_limiters<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<LimitersResponse> {
+    let cfg = read_config(&deps.storage)?;
+    Ok(LimitersResponse {
+        limiters: cfg.limiters,
+    })
+}
+
 fn query_config<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
 ) -> StdResult<ConfigResponse> {
@@ -36,8 +64,8 @@ fn query_target<S: Storage, A: Api, Q: Querier>(
 fn query_staged_amount<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     account: &HumanAddr,
-    asset: &HumanAddr,
+    asset: &AssetInfo,
 ) -> StdResult<StagedAmountResponse> {
-    let staged_amount = read_staged_asset(&deps.storage, account, asset)?;
+    let staged_amount = read_staged_asset(&deps.storage, account, &asset.as_addr())?;
     Ok(StagedAmountResponse { staged_amount })
 }
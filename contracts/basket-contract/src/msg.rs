@@ -1,11 +1,227 @@
 use basket_math::FPDecimal;
-use cosmwasm_std::{HumanAddr, Uint128};
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{
+    to_binary, BankMsg, Coin, CosmosMsg, HumanAddr, StdResult, Uint128, WasmMsg,
+};
+use cw20::{Cw20HandleMsg, Cw20ReceiveMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::state::{BasketConfig, PenaltyParams};
 
+/// A basket constituent, either a CW20 token identified by its contract
+/// address or a native coin identified by its denom.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Cw20 { contract_addr: HumanAddr },
+    Native { denom: String },
+}
+
+/// A sliding-window composition-change limiter for a single component. It caps
+/// how far an asset's post-operation weight may exceed either a static
+/// `upper_boundary` or the moving-average `baseline()` accumulated over
+/// `divisions` sub-intervals of `window`, blunting oracle-manipulation and
+/// sandwich attacks against mint/burn.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Limiter {
+    /// Length of the sliding window, in seconds.
+    pub window: u64,
+    /// Number of sub-intervals the window is divided into.
+    pub divisions: u64,
+    /// Maximum weight permitted above the moving-average baseline.
+    pub max_deviation: FPDecimal,
+    /// Optional static upper bound on the weight, independent of the baseline.
+    pub upper_boundary: Option<FPDecimal>,
+    /// Per-division weight observations (the latest weight recorded in each
+    /// division), oldest first.
+    pub observations: Vec<Observation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Observation {
+    /// Start time of the division this observation belongs to.
+    pub start: u64,
+    /// Weight recorded for the division.
+    pub weight: FPDecimal,
+}
+
+impl Limiter {
+    fn division_len(&self) -> u64 {
+        (self.window / self.divisions.max(1)).max(1)
+    }
+
+    /// Evicts observations that have aged out of the window relative to `now`.
+    fn evict(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(self.window);
+        self.observations.retain(|o| o.start >= cutoff);
+    }
+
+    /// Moving-average weight over the live observations.
+    pub fn baseline(&self) -> FPDecimal {
+        if self.observations.is_empty() {
+            return FPDecimal::zero();
+        }
+        let sum = self
+            .observations
+            .iter()
+            .fold(FPDecimal::zero(), |acc, o| acc + o.weight);
+        sum / FPDecimal::from(self.observations.len() as u128)
+    }
+
+    /// Whether `weight` is within the static and moving-average bounds.
+    pub fn permits(&self, weight: FPDecimal, now: u64) -> bool {
+        if let Some(upper) = self.upper_boundary {
+            if weight > upper {
+                return false;
+            }
+        }
+        if self.observations.is_empty() {
+            return true;
+        }
+        let mut snapshot = self.clone();
+        snapshot.evict(now);
+        if snapshot.observations.is_empty() {
+            return true;
+        }
+        weight <= snapshot.baseline() + self.max_deviation
+    }
+
+    /// Records a newly observed `weight`, advancing/evicting divisions by time.
+    pub fn record(&mut self, weight: FPDecimal, now: u64) {
+        self.evict(now);
+        let len = self.division_len();
+        let div_start = now - (now % len);
+        match self.observations.last_mut() {
+            Some(o) if o.start == div_start => o.weight = weight,
+            _ => self.observations.push(Observation {
+                start: div_start,
+                weight,
+            }),
+        }
+    }
+}
+
+/// An `AssetInfo`-keyed limiter as stored in config and surfaced by queries.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LimiterEntry {
+    pub asset: AssetInfo,
+    pub limiter: Limiter,
+}
+
+/// The parameters governance supplies when registering or updating a limiter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LimiterParams {
+    pub window: u64,
+    pub divisions: u64,
+    pub max_deviation: FPDecimal,
+    pub upper_boundary: Option<FPDecimal>,
+}
+
+/// Operating status of the basket, used as a killswitch during oracle outages
+/// or detected price manipulation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatus {
+    /// All operations permitted.
+    Operational,
+    /// Mint and burn are halted; staging and unstaging still work so users can
+    /// recover funds.
+    StopMintBurn,
+    /// Everything is frozen.
+    StopAll,
+}
+
+impl ContractStatus {
+    pub fn mint_burn_allowed(&self) -> bool {
+        matches!(self, ContractStatus::Operational)
+    }
+
+    pub fn staging_allowed(&self) -> bool {
+        !matches!(self, ContractStatus::StopAll)
+    }
+}
+
+/// A concrete amount of a given asset, the terraswap-style pairing of
+/// `AssetInfo` with a quantity.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint128,
+}
+
+impl AssetInfo {
+    pub fn is_native(&self) -> bool {
+        matches!(self, AssetInfo::Native { .. })
+    }
+
+    /// Stable key used to index staged amounts and inventory by asset.
+    pub fn as_key(&self) -> String {
+        match self {
+            AssetInfo::Cw20 { contract_addr } => contract_addr.to_string(),
+            AssetInfo::Native { denom } => denom.clone(),
+        }
+    }
+
+    /// The staging/oracle storage key as a `HumanAddr` so native and token legs
+    /// can share the existing address-keyed buckets.
+    pub fn as_addr(&self) -> HumanAddr {
+        HumanAddr(self.as_key())
+    }
+
+    /// Asserts that exactly `amount` of this native asset (and nothing else of
+    /// this denom) was attached to the message, mirroring the terraswap native
+    /// deposit guard. CW20 legs never carry `sent_funds`, so they are a no-op.
+    pub fn assert_sent_native_token(
+        &self,
+        sent_funds: &[Coin],
+        amount: Uint128,
+    ) -> StdResult<()> {
+        if let AssetInfo::Native { denom } = self {
+            let sent = sent_funds
+                .iter()
+                .find(|c| &c.denom == denom)
+                .map(|c| c.amount)
+                .unwrap_or_else(Uint128::zero);
+            if sent != amount {
+                return Err(cosmwasm_std::StdError::generic_err(format!(
+                    "Native token balance mismatch for {}: attached {}, declared {}",
+                    denom, sent, amount
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the message that moves `amount` of this asset to `recipient`:
+    /// a `BankMsg::Send` for native legs and a `Cw20HandleMsg::Transfer` for
+    /// token legs.
+    pub fn transfer_msg(
+        &self,
+        from: &HumanAddr,
+        recipient: &HumanAddr,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        match self {
+            AssetInfo::Native { denom } => Ok(CosmosMsg::Bank(BankMsg::Send {
+                from_address: from.clone(),
+                to_address: recipient.clone(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount,
+                }],
+            })),
+            AssetInfo::Cw20 { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.clone(),
+                msg: to_binary(&Cw20HandleMsg::Transfer {
+                    recipient: recipient.clone(),
+                    amount,
+                })?,
+                send: vec![],
+            })),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     /// Basket name (title)
@@ -17,8 +233,8 @@ pub struct InitMsg {
     /// Basket token CW20 address
     pub basket_token: Option<HumanAddr>,
 
-    /// Asset addresses
-    pub assets: Vec<HumanAddr>,
+    /// Basket constituents (CW20 tokens and/or native denoms)
+    pub assets: Vec<AssetInfo>,
 
     /// Oracle address
     pub oracle: HumanAddr,
@@ -35,9 +251,15 @@ pub struct InitMsg {
 pub enum HandleMsg {
     Receive(Cw20ReceiveMsg),
 
+    /// Stages a native coin directly (bypassing the Cw20 `Receive` path); the
+    /// declared `asset.amount` must match the coins attached as `sent_funds`.
+    StageNativeAsset {
+        asset: Asset,
+    },
+
     /// Withdraws asset from staging
     UnstageAsset {
-        asset: HumanAddr,
+        asset: AssetInfo,
         amount: Option<Uint128>,
     },
 
@@ -46,12 +268,53 @@ pub enum HandleMsg {
         basket_token: HumanAddr,
     },
 
+    /// Governance-only killswitch controlling which operations are permitted
+    SetContractStatus {
+        status: ContractStatus,
+    },
+
+    /// Governance-only: flag a component for delisting. Staging it is rejected
+    /// and its target weight is forced to zero; once its basket balance drains
+    /// to zero it is removed from `assets`/`target` automatically.
+    MarkAssetForRemoval {
+        asset: AssetInfo,
+    },
+
+    /// Governance-only: register (or replace) a composition-change limiter for
+    /// a component asset.
+    RegisterLimiter {
+        asset: AssetInfo,
+        params: LimiterParams,
+    },
+
+    /// Governance-only: update an existing limiter's parameters in place,
+    /// preserving its accumulated observations.
+    UpdateLimiter {
+        asset: AssetInfo,
+        params: LimiterParams,
+    },
+
+    /// Governance-only: remove a limiter.
+    DeregisterLimiter {
+        asset: AssetInfo,
+    },
+
     /// Can be called by the owner to reset the basket weight target
     ResetTarget {
         assets: Vec<HumanAddr>,
         target: Vec<u32>,
     },
 
+    /// Swap one component for another inside the basket. Used for a native
+    /// `offer_asset`; `amount` must match the coins attached as `sent_funds`.
+    /// CW20 offers go through [`Cw20HookMsg::Swap`].
+    Swap {
+        offer_asset: AssetInfo,
+        ask_asset: AssetInfo,
+        amount: Uint128,
+        min_return: Option<Uint128>,
+    },
+
     /// Mints new assets
     Mint {
         /// Asset amounts deposited for minting (must be staged)
@@ -75,6 +338,12 @@ pub enum Cw20HookMsg {
         /// optional proposed set of weights to use
         asset_weights: Option<Vec<u32>>,
     },
+
+    /// Swap the received CW20 component for `ask_asset` inside the basket.
+    Swap {
+        ask_asset: AssetInfo,
+        min_return: Option<Uint128>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -84,11 +353,22 @@ pub enum QueryMsg {
     Target {},
     StagedAmount {
         account: HumanAddr,
-        asset: HumanAddr,
+        asset: AssetInfo,
     },
     BasketState {
         basket_contract_address: HumanAddr,
     },
+    Limiters {},
+    TransactionHistory {
+        address: HumanAddr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LimitersResponse {
+    pub limiters: Vec<LimiterEntry>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -96,6 +376,38 @@ pub struct ConfigResponse {
     pub config: BasketConfig,
 }
 
+/// The kind of basket operation a [`TransactionRecord`] captures.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxAction {
+    Mint,
+    Burn,
+    Stage,
+    Unstage,
+    Swap,
+}
+
+/// A rich, on-chain record of a single basket operation, keyed by the acting
+/// user, so front-ends and accounting tools get a reliable ledger without
+/// scraping events.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransactionRecord {
+    pub action: TxAction,
+    /// Component assets moved and their amounts.
+    pub assets: Vec<(AssetInfo, Uint128)>,
+    /// Basket tokens minted or burned; `action` tells which.
+    pub basket_tokens: Uint128,
+    pub score: Option<FPDecimal>,
+    pub penalty: Option<FPDecimal>,
+    pub height: u64,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransactionHistoryResponse {
+    pub history: Vec<TransactionRecord>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TargetResponse {
     pub target: Vec<u32>,
@@ -112,6 +424,6 @@ pub struct BasketStateResponse {
     pub outstanding_balance_tokens: Uint128,
     pub prices: Vec<FPDecimal>,
     pub inv: Vec<Uint128>,
-    pub assets: Vec<HumanAddr>,
+    pub assets: Vec<AssetInfo>,
     pub target: Vec<u32>,
 }
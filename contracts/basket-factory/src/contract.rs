@@ -1,71 +1,89 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+
 use cosmwasm_std::{
-    log, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Decimal, Env, Extern, HandleResponse,
-    HandleResult, HumanAddr, InitResponse, MigrateResponse, MigrateResult, Querier, StdError,
-    StdResult, Storage, Uint128, WasmMsg,
+    attr, to_binary, Addr, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, QueryRequest, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    WasmQuery,
 };
+use cw_utils::parse_reply_instantiate_data;
 
-use crate::querier::{load_mint_asset_config, load_oracle_feeder};
+use crate::querier::load_oracle_feeder;
 use crate::state::{
-    decrease_total_weight, increase_total_weight, read_all_weight, read_config,
-    read_last_distributed, read_params, read_total_weight, read_weight, remove_params,
-    remove_weight, store_config, store_last_distributed, store_params, store_total_weight,
-    store_weight, Config,
+    decrease_total_weight, increase_total_weight, read_all_weight, read_cluster_params,
+    read_cluster_token_id, read_config, read_guardian_set, read_last_distributed, read_params,
+    read_pyth_price, read_tmp_cluster, read_total_weight, read_weight, remove_params, remove_weight,
+    archive_vaa, is_vaa_archived, store_cluster_token_id, store_config, store_last_distributed,
+    store_params, store_pyth_price, store_tmp_cluster, store_total_weight, store_weight, Config,
+    PythPrice,
 };
 
+use sha3::{Digest, Keccak256};
+
 use crate::msg::{
-    BasketHandleMsg, BasketInitMsg, ConfigResponse, DistributionInfoResponse, HandleMsg, InitMsg,
-    MigrateMsg, Params, QueryMsg, StakingHandleMsg, StakingCw20HookMsg
+    BasketExecuteMsg, BasketInstantiateMsg, ConfigResponse, DistributionAmountResponse,
+    DistributionInfoResponse, EmissionCurve, ExecuteMsg, InstantiateMsg, MigrateMsg, Params,
+    QueryMsg, StakingCw20HookMsg, StakingExecuteMsg,
 };
-// use mirror_protocol::mint::HandleMsg as MintHandleMsg;
-// use mirror_protocol::oracle::HandleMsg as OracleHandleMsg;
-// use mirror_protocol::staking::Cw20HookMsg as StakingCw20HookMsg;
-// use mirror_protocol::staking::HandleMsg as StakingHandleMsg;
 
-use cw20::{Cw20HandleMsg, MinterResponse};
+use cw1155::Cw1155ExecuteMsg;
+use cw20::{Cw20ExecuteMsg, MinterResponse};
 use terraswap::asset::{AssetInfo, PairInfo};
-use terraswap::factory::HandleMsg as TerraswapFactoryHandleMsg;
-use terraswap::hook::InitHook;
+use terraswap::factory::ExecuteMsg as TerraswapFactoryExecuteMsg;
 use terraswap::querier::query_pair_info;
-use terraswap::token::InitMsg as TokenInitMsg;
+use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
 
 const NEBULA_TOKEN_WEIGHT: u32 = 300u32;
 const NORMAL_TOKEN_WEIGHT: u32 = 30u32;
 const DISTRIBUTION_INTERVAL: u64 = 1u64;
 
-pub fn init<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
+// reply ids for the deterministic cluster-creation flow: the cluster contract
+// instantiation, then the cluster-token cw20 instantiation.
+const CLUSTER_REPLY_ID: u64 = 1;
+const CLUSTER_TOKEN_REPLY_ID: u64 = 2;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
     env: Env,
-    msg: InitMsg,
-) -> StdResult<InitResponse> {
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
     store_config(
-        &mut deps.storage,
+        deps.storage,
         &Config {
-            owner: CanonicalAddr::default(),
-            nebula_token: CanonicalAddr::default(),
-            oracle_contract: CanonicalAddr::default(),
-            terraswap_factory: CanonicalAddr::default(),
-            staking_contract: CanonicalAddr::default(),
-            commission_collector: CanonicalAddr::default(),
+            owner: CanonicalAddr::from(vec![]),
+            nebula_token: CanonicalAddr::from(vec![]),
+            oracle_contract: CanonicalAddr::from(vec![]),
+            terraswap_factory: CanonicalAddr::from(vec![]),
+            staking_contract: CanonicalAddr::from(vec![]),
+            commission_collector: CanonicalAddr::from(vec![]),
             token_code_id: msg.token_code_id,
             cluster_code_id: msg.cluster_code_id,
             base_denom: msg.base_denom,
-            genesis_time: env.block.time,
+            genesis_time: env.block.time.seconds(),
             distribution_schedule: msg.distribution_schedule,
+            multitoken_contract: None,
+            emission_curve: None,
+            emitter_allowlist: vec![],
+            max_price_age: msg.max_price_age,
         },
     )?;
 
-    store_total_weight(&mut deps.storage, 0u32)?;
-    store_last_distributed(&mut deps.storage, env.block.time)?;
-    Ok(InitResponse::default())
+    store_total_weight(deps.storage, 0u32)?;
+    store_last_distributed(deps.storage, env.block.time.seconds())?;
+    Ok(Response::default())
 }
 
-pub fn handle<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
     env: Env,
-    msg: HandleMsg,
-) -> StdResult<HandleResponse> {
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
     match msg {
-        HandleMsg::PostInitialize {
+        ExecuteMsg::PostInitialize {
             owner,
             nebula_token,
             oracle_contract,
@@ -74,7 +92,6 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             commission_collector,
         } => post_initialize(
             deps,
-            env,
             owner,
             nebula_token,
             oracle_contract,
@@ -82,89 +99,91 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             staking_contract,
             commission_collector,
         ),
-        HandleMsg::UpdateConfig {
+        ExecuteMsg::UpdateConfig {
+            owner,
+            token_code_id,
+            cluster_code_id,
+            distribution_schedule,
+        } => update_config(
+            deps,
+            info,
             owner,
             token_code_id,
             cluster_code_id,
             distribution_schedule,
-        } => update_config(deps, env, owner, token_code_id, cluster_code_id, distribution_schedule),
-        HandleMsg::UpdateWeight {
+        ),
+        ExecuteMsg::UpdateWeight {
             asset_token,
             weight,
-        } => update_weight(deps, env, asset_token, weight),
-        HandleMsg::CreateCluster {
+        } => update_weight(deps, info, asset_token, weight),
+        ExecuteMsg::CreateCluster { params } => create_cluster(deps, env, info, params),
+        ExecuteMsg::AttestForeignAsset { vaa } => attest_foreign_asset(deps, env, vaa),
+        ExecuteMsg::RegisterAssetFromVAA { vaa } => register_asset_from_vaa(deps, env, vaa),
+        ExecuteMsg::RegisterMigration {
+            asset_token,
+            price_feed_id,
+        } => register_migration(deps, env, info, asset_token, price_feed_id),
+        ExecuteMsg::UpdatePythPrice { attestation } => update_pyth_price(deps, env, attestation),
+        ExecuteMsg::RegisterClusterId { cluster } => register_cluster_id(deps, info, cluster),
+        ExecuteMsg::DecommissionCluster {
+            cluster_token,
+            end_price,
+        } => decommission_cluster(deps, info, cluster_token, end_price),
+        ExecuteMsg::MigrateCluster {
             name,
             symbol,
-            params,
-        } => create_cluster(deps, env, params),
-        HandleMsg::TokenCreationHook { } => {
-            token_creation_hook(deps, env)
-        }
-        HandleMsg::SetBasketTokenHook { cluster } => {
-            set_basket_token_hook(deps, env, cluster)
-        }
-        HandleMsg::TerraswapCreationHook { asset_token } => {
-            terraswap_creation_hook(deps, env, asset_token)
+            from_cluster,
+            end_price,
+        } => migrate_cluster(deps, env, info, name, symbol, from_cluster, end_price),
+        ExecuteMsg::TerraswapCreationHook { asset_token } => {
+            terraswap_creation_hook(deps, info, asset_token)
         }
-        HandleMsg::Distribute {} => distribute(deps, env),
-        HandleMsg::PassCommand { contract_addr, msg } => {
-            pass_command(deps, env, contract_addr, msg)
-        }
-        // HandleMsg::RevokeAsset {
-        //     asset_token,
-        //     end_price,
-        // } => revoke_asset(deps, env, asset_token, end_price),
-        // HandleMsg::MigrateAsset {
-        //     name,
-        //     symbol,
-        //     from_token,
-        //     end_price,
-        // } => migrate_asset(deps, env, name, symbol, from_token, end_price),
+        ExecuteMsg::Distribute {} => distribute(deps, env),
+        ExecuteMsg::PassCommand { contract_addr, msg } => pass_command(deps, info, contract_addr, msg),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn post_initialize<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    _env: Env,
-    owner: HumanAddr,
-    nebula_token: HumanAddr,
-    oracle_contract: HumanAddr,
-    terraswap_factory: HumanAddr,
-    staking_contract: HumanAddr,
-    commission_collector: HumanAddr,
-) -> HandleResult {
-    let mut config: Config = read_config(&deps.storage)?;
-    if config.owner != CanonicalAddr::default() {
-        return Err(StdError::unauthorized());
-    }
-
-    config.owner = deps.api.canonical_address(&owner)?;
-    config.nebula_token = deps.api.canonical_address(&nebula_token)?;
-    config.oracle_contract = deps.api.canonical_address(&oracle_contract)?;
-    config.terraswap_factory = deps.api.canonical_address(&terraswap_factory)?;
-    config.staking_contract = deps.api.canonical_address(&staking_contract)?;
-    config.commission_collector = deps.api.canonical_address(&commission_collector)?;
-    store_config(&mut deps.storage, &config)?;
-
-    Ok(HandleResponse::default())
-}
-
-pub fn update_config<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    owner: Option<HumanAddr>,
+pub fn post_initialize(
+    deps: DepsMut,
+    owner: String,
+    nebula_token: String,
+    oracle_contract: String,
+    terraswap_factory: String,
+    staking_contract: String,
+    commission_collector: String,
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    if config.owner != CanonicalAddr::from(vec![]) {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    config.owner = deps.api.addr_canonicalize(&owner)?;
+    config.nebula_token = deps.api.addr_canonicalize(&nebula_token)?;
+    config.oracle_contract = deps.api.addr_canonicalize(&oracle_contract)?;
+    config.terraswap_factory = deps.api.addr_canonicalize(&terraswap_factory)?;
+    config.staking_contract = deps.api.addr_canonicalize(&staking_contract)?;
+    config.commission_collector = deps.api.addr_canonicalize(&commission_collector)?;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
     token_code_id: Option<u64>,
     cluster_code_id: Option<u64>,
     distribution_schedule: Option<Vec<(u64, u64, Uint128)>>,
-) -> HandleResult {
-    let mut config: Config = read_config(&deps.storage)?;
-    if config.owner != deps.api.canonical_address(&env.message.sender)? {
-        return Err(StdError::unauthorized());
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
     }
 
     if let Some(owner) = owner {
-        config.owner = deps.api.canonical_address(&owner)?;
+        config.owner = deps.api.addr_canonicalize(&owner)?;
     }
 
     if let Some(distribution_schedule) = distribution_schedule {
@@ -179,68 +198,500 @@ pub fn update_config<S: Storage, A: Api, Q: Querier>(
         config.cluster_code_id = cluster_code_id;
     }
 
-    store_config(&mut deps.storage, &config)?;
+    store_config(deps.storage, &config)?;
 
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![log("action", "update_config")],
-        data: None,
-    })
+    Ok(Response::new().add_attribute("action", "update_config"))
 }
 
-pub fn update_weight<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    asset_token: HumanAddr,
+pub fn update_weight(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_token: String,
     weight: u32,
-) -> HandleResult {
-    let config: Config = read_config(&deps.storage)?;
-    if config.owner != deps.api.canonical_address(&env.message.sender)? {
-        return Err(StdError::unauthorized());
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
     }
 
-    let asset_token_raw = deps.api.canonical_address(&asset_token)?;
-    let origin_weight = read_weight(&deps.storage, &asset_token_raw)?;
-    store_weight(&mut deps.storage, &asset_token_raw, weight)?;
+    let asset_token_raw = deps.api.addr_canonicalize(&asset_token)?;
+    let origin_weight = read_weight(deps.storage, &asset_token_raw)?;
+    store_weight(deps.storage, &asset_token_raw, weight)?;
+
+    let origin_total_weight = read_total_weight(deps.storage)?;
+    // checked arithmetic so a bad weight update returns an error instead of
+    // wrapping the total weight.
+    let new_total_weight = origin_total_weight
+        .checked_add(weight)
+        .ok_or_else(|| StdError::generic_err("total weight overflow"))?
+        .checked_sub(origin_weight)
+        .ok_or_else(|| StdError::generic_err("total weight underflow"))?;
+    store_total_weight(deps.storage, new_total_weight)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_weight"),
+        attr("asset_token", asset_token),
+        attr("weight", weight.to_string()),
+    ]))
+}
 
-    let origin_total_weight = read_total_weight(&deps.storage)?;
-    store_total_weight(
-        &mut deps.storage,
-        origin_total_weight + weight - origin_weight,
-    )?;
+// just for by passing command to other contract like update config
+pub fn pass_command(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract_addr: String,
+    msg: Binary,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    Ok(Response::new().add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg,
+        funds: vec![],
+    })))
+}
+
+/// A parsed Wormhole VAA: the guardian-set index the signatures belong to and
+/// the body (the signed portion that follows the signature block).
+struct ParsedVaa {
+    guardian_set_index: u32,
+    /// `(guardian_index, 65-byte r‖s‖recovery_id)` pairs, in file order.
+    signatures: Vec<(u8, Vec<u8>)>,
+    /// The portion of the blob the signatures cover: timestamp, nonce,
+    /// emitter_chain, emitter_address, sequence, consistency_level, payload.
+    body: Vec<u8>,
+}
 
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![
-            log("action", "update_weight"),
-            log("asset_token", asset_token),
-            log("weight", weight),
-        ],
-        data: None,
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(hasher.finalize().as_slice());
+    out
+}
+
+/// Parse a VAA blob into its header and body. Layout: version (1),
+/// guardian_set_index (u32 BE), num_signatures (1), then per signature
+/// guardian_index (1) + 65-byte signature; everything after is the body.
+fn parse_vaa(vaa: &[u8]) -> StdResult<ParsedVaa> {
+    if vaa.len() < 6 {
+        return Err(StdError::generic_err("VAA too short"));
+    }
+    // version (1) + guardian_set_index (4) + num_signatures (1)
+    let guardian_set_index = u32::from_be_bytes([vaa[1], vaa[2], vaa[3], vaa[4]]);
+    let num_signatures = vaa[5] as usize;
+
+    let mut pos = 6usize;
+    let mut signatures = Vec::with_capacity(num_signatures);
+    for _ in 0..num_signatures {
+        // guardian_index (1) + signature (65)
+        if pos + 66 > vaa.len() {
+            return Err(StdError::generic_err("truncated signature block"));
+        }
+        let guardian_index = vaa[pos];
+        let sig = vaa[pos + 1..pos + 66].to_vec();
+        signatures.push((guardian_index, sig));
+        pos += 66;
+    }
+
+    Ok(ParsedVaa {
+        guardian_set_index,
+        signatures,
+        body: vaa[pos..].to_vec(),
     })
 }
 
-// just for by passing command to other contract like update config
-pub fn pass_command<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
+/// Verify a VAA against the guardian set stored at its `guardian_set_index`,
+/// returning the signed body (header fields followed by the payload) on
+/// success. Requires at least `floor(2/3 * n) + 1`
+/// valid signatures whose guardian indices are strictly increasing.
+fn verify_vaa(deps: Deps, vaa: &[u8]) -> StdResult<Vec<u8>> {
+    let parsed = parse_vaa(vaa)?;
+    let guardians = read_guardian_set(deps.storage, parsed.guardian_set_index)?;
+
+    let quorum = guardians.len() * 2 / 3 + 1;
+    // Wormhole signs the double keccak of the body.
+    let hash = keccak256(&keccak256(&parsed.body));
+
+    let mut valid = 0usize;
+    let mut last_index: Option<u8> = None;
+    for (guardian_index, sig) in parsed.signatures.iter() {
+        // indices must be strictly increasing so a single guardian cannot be
+        // counted twice.
+        if let Some(prev) = last_index {
+            if *guardian_index <= prev {
+                return Err(StdError::generic_err("signature indices not increasing"));
+            }
+        }
+        last_index = Some(*guardian_index);
+
+        let expected = guardians
+            .get(*guardian_index as usize)
+            .ok_or_else(|| StdError::generic_err("guardian index out of range"))?;
+
+        let recovered = recover_guardian_address(&hash, sig)?;
+        if &recovered == expected {
+            valid += 1;
+        }
+    }
+
+    if valid < quorum {
+        return Err(StdError::generic_err(format!(
+            "insufficient guardian signatures: {} of {} required",
+            valid, quorum
+        )));
+    }
+
+    Ok(parsed.body)
+}
+
+/// Recover the 20-byte guardian (ethereum-style) address from a 65-byte
+/// secp256k1 signature `r‖s‖recovery_id` over `hash`.
+fn recover_guardian_address(hash: &[u8; 32], sig: &[u8]) -> StdResult<[u8; 20]> {
+    use k256::ecdsa::recoverable;
+
+    if sig.len() != 65 {
+        return Err(StdError::generic_err("signature must be 65 bytes"));
+    }
+    let recovery_id =
+        recoverable::Id::new(sig[64]).map_err(|_| StdError::generic_err("invalid recovery id"))?;
+    let signature = recoverable::Signature::new(
+        &k256::ecdsa::Signature::from_scalars(
+            <[u8; 32]>::try_from(&sig[0..32]).unwrap(),
+            <[u8; 32]>::try_from(&sig[32..64]).unwrap(),
+        )
+        .map_err(|_| StdError::generic_err("invalid signature scalars"))?,
+        recovery_id,
+    )
+    .map_err(|_| StdError::generic_err("invalid recoverable signature"))?;
+
+    let verifying_key = signature
+        .recover_verifying_key_from_digest_bytes(hash.into())
+        .map_err(|_| StdError::generic_err("failed to recover public key"))?;
+
+    // drop the SEC1 tag byte; the guardian address is the last 20 bytes of the
+    // keccak of the 64-byte uncompressed public key.
+    let pubkey = verifying_key.to_encoded_point(false);
+    let digest = keccak256(&pubkey.as_bytes()[1..]);
+    let mut addr = [0u8; 20];
+    addr.copy_from_slice(&digest[12..]);
+    Ok(addr)
+}
+
+/// AttestForeignAsset
+/// Verifies a guardian-signed Wormhole VAA and, on success, admits the wrapped
+/// asset it describes by running the standard cw20 instantiation + terraswap
+/// pair flow used by `create_cluster`.
+pub fn attest_foreign_asset(deps: DepsMut, env: Env, vaa: Binary) -> StdResult<Response> {
+    let body = verify_vaa(deps.as_ref(), vaa.as_slice())?;
+
+    // the VAA body carries the standard Wormhole header ahead of the payload:
+    // timestamp (4) + nonce (4) + emitter_chain (2) + emitter_address (32) +
+    // sequence (8) + consistency_level (1), then the asset-metadata payload.
+    const HEADER_LEN: usize = 4 + 4 + 2 + 32 + 8 + 1;
+    if body.len() <= HEADER_LEN {
+        return Err(StdError::generic_err("VAA payload missing"));
+    }
+    let payload = &body[HEADER_LEN..];
+    let params = decode_foreign_asset_payload(payload)?;
+
+    if read_params(deps.storage).is_ok() {
+        return Err(StdError::generic_err("A whitelist process is in progress"));
+    }
+    let cluster_code_id = read_config(deps.storage)?.cluster_code_id;
+    store_params(deps.storage, &params)?;
+
+    // Instantiate the cluster and handle its address in `reply` so the
+    // cw20/terraswap wiring runs and the params lock is released, exactly like
+    // `create_cluster`.
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: None,
+                code_id: cluster_code_id,
+                funds: vec![],
+                label: params.name.clone(),
+                msg: to_binary(&BasketInstantiateMsg {
+                    name: params.name.clone(),
+                    owner: env.contract.address.to_string(),
+                    assets: params.assets.clone(),
+                    oracle: params.oracle.clone(),
+                    penalty: params.penalty,
+                    basket_token: None,
+                    target: params.target.clone(),
+                })?,
+            }),
+            CLUSTER_REPLY_ID,
+        ))
+        .add_attributes(vec![
+            attr("action", "attest_foreign_asset"),
+            attr("name", params.name),
+            attr("symbol", params.symbol),
+        ]))
+}
+
+/// RegisterAssetFromVAA
+/// Authorizes an asset delisting/migration via a signed Wormhole VAA instead of
+/// the local owner. The emitter must be allow-listed, each VAA is consumed at
+/// most once (replay archive), and the payload carries `{ asset_token,
+/// end_price }` which is settled through the same path as `RegisterMigration`.
+pub fn register_asset_from_vaa(deps: DepsMut, _env: Env, vaa: Binary) -> StdResult<Response> {
+    // dedupe replays before doing any work
+    let vaa_hash = keccak256(vaa.as_slice());
+    if is_vaa_archived(deps.storage, &vaa_hash)? {
+        return Err(StdError::generic_err("VAA already processed"));
+    }
+
+    let body = verify_vaa(deps.as_ref(), vaa.as_slice())?;
+
+    // emitter_chain (u16) follows timestamp (4) + nonce (4); emitter_address is
+    // the next 32 bytes.
+    const HEADER_LEN: usize = 4 + 4 + 2 + 32 + 8 + 1;
+    if body.len() <= HEADER_LEN {
+        return Err(StdError::generic_err("VAA payload missing"));
+    }
+    let emitter_chain = u16::from_be_bytes([body[8], body[9]]);
+    let mut emitter_address = [0u8; 32];
+    emitter_address.copy_from_slice(&body[10..42]);
+
+    let config: Config = read_config(deps.storage)?;
+    if !config
+        .emitter_allowlist
+        .iter()
+        .any(|(chain, addr)| *chain == emitter_chain && addr == &emitter_address)
+    {
+        return Err(StdError::generic_err("emitter not allow-listed"));
+    }
+
+    let payload: MigrationPayload = cosmwasm_std::from_slice(&body[HEADER_LEN..])?;
+
+    // mark consumed, then settle the asset at the attested end price
+    archive_vaa(deps.storage, &vaa_hash)?;
+
+    let asset_token_raw = deps.api.addr_canonicalize(&payload.asset_token)?;
+    let weight = read_weight(deps.storage, &asset_token_raw)?;
+    remove_weight(deps.storage, &asset_token_raw);
+    decrease_total_weight(deps.storage, weight)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: payload.asset_token.clone(),
+            funds: vec![],
+            msg: to_binary(&BasketExecuteMsg::Decommission {
+                end_price: payload.end_price,
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "register_asset_from_vaa"),
+            attr("asset_token", payload.asset_token),
+            attr("end_price", payload.end_price.to_string()),
+        ]))
+}
+
+/// Payload of a `RegisterAssetFromVAA` message: the asset to settle and the
+/// fixed end price to settle it at.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MigrationPayload {
+    asset_token: String,
+    end_price: Decimal,
+}
+
+/// RegisterMigration
+/// Delists/migrates `asset_token` against a live attested price instead of a
+/// governance-chosen number: the Pyth-on-Terra oracle (`oracle_contract`) is
+/// queried for `price_feed_id`, the price is staleness-checked and scaled by
+/// its exponent into a `Decimal`, and the result is used as the end price.
+pub fn register_migration(
+    deps: DepsMut,
     env: Env,
-    contract_addr: HumanAddr,
-    msg: Binary,
-) -> HandleResult {
-    let config: Config = read_config(&deps.storage)?;
-    if config.owner != deps.api.canonical_address(&env.message.sender)? {
-        return Err(StdError::unauthorized());
-    }
-
-    Ok(HandleResponse {
-        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr,
-            msg,
-            send: vec![],
-        })],
-        log: vec![],
-        data: None,
-    })
+    info: MessageInfo,
+    asset_token: String,
+    price_feed_id: Binary,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let oracle = deps.api.addr_humanize(&config.oracle_contract)?;
+    let res: PythPriceFeedResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle.to_string(),
+        msg: to_binary(&PythQueryMsg::PriceFeed { id: price_feed_id })?,
+    }))?;
+    let feed = res.price_feed;
+
+    let now = env.block.time.seconds();
+    if now.saturating_sub(feed.publish_time) > config.max_price_age {
+        return Err(StdError::generic_err("pyth price is stale"));
+    }
+    let end_price = normalize_pyth_price(feed.price, feed.expo)?;
+
+    let asset_token_raw = deps.api.addr_canonicalize(&asset_token)?;
+    let weight = read_weight(deps.storage, &asset_token_raw)?;
+    remove_weight(deps.storage, &asset_token_raw);
+    decrease_total_weight(deps.storage, weight)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset_token.clone(),
+            funds: vec![],
+            msg: to_binary(&BasketExecuteMsg::Decommission { end_price })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "register_migration"),
+            attr("asset_token", asset_token),
+            attr("end_price", end_price.to_string()),
+        ]))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PythQueryMsg {
+    PriceFeed { id: Binary },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PythPriceFeedResponse {
+    price_feed: PythFeed,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PythFeed {
+    price: i64,
+    expo: i32,
+    publish_time: u64,
+}
+
+/// Normalize a Pyth `(price, expo)` pair into a `Decimal` holding the real
+/// value `price * 10^expo`. No further decimal shift is applied.
+fn normalize_pyth_price(price: i64, expo: i32) -> StdResult<Decimal> {
+    if price < 0 {
+        return Err(StdError::generic_err("negative price"));
+    }
+    let price = Uint128::from(price as u128);
+    if expo >= 0 {
+        let scale = Uint128::from(10u128.pow(expo as u32));
+        Ok(Decimal::from_ratio(price * scale, 1u128))
+    } else {
+        let scale = Uint128::from(10u128.pow((-expo) as u32));
+        Ok(Decimal::from_ratio(price, scale))
+    }
+}
+
+/// UpdatePythPrice
+/// Verifies a guardian-signed price attestation (same VAA machinery as
+/// `attest_foreign_asset`) and caches the reported price keyed by feed id so
+/// `distribute` and clusters can read staleness-checked prices.
+pub fn update_pyth_price(deps: DepsMut, _env: Env, attestation: Binary) -> StdResult<Response> {
+    let body = verify_vaa(deps.as_ref(), attestation.as_slice())?;
+
+    // the price attestation is carried directly as the VAA payload
+    const HEADER_LEN: usize = 4 + 4 + 2 + 32 + 8 + 1;
+    if body.len() <= HEADER_LEN {
+        return Err(StdError::generic_err("attestation payload missing"));
+    }
+    let (feed_id, price) = decode_price_attestation(&body[HEADER_LEN..])?;
+    store_pyth_price(deps.storage, &feed_id, &price)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_pyth_price"),
+        attr("price", price.price.to_string()),
+        attr("publish_time", price.publish_time.to_string()),
+    ]))
+}
+
+/// Decode a Pyth-style price attestation payload into its feed id and the cached
+/// price record. Layout: price_feed_id (32) + price (i64) + confidence (u64) +
+/// exponent (i32) + ema_price (i64) + publish_time (i64), all big-endian.
+fn decode_price_attestation(payload: &[u8]) -> StdResult<([u8; 32], PythPrice)> {
+    const LEN: usize = 32 + 8 + 8 + 4 + 8 + 8;
+    if payload.len() < LEN {
+        return Err(StdError::generic_err("malformed price attestation"));
+    }
+    let mut feed_id = [0u8; 32];
+    feed_id.copy_from_slice(&payload[0..32]);
+
+    let price = i64::from_be_bytes(payload[32..40].try_into().unwrap());
+    let confidence = u64::from_be_bytes(payload[40..48].try_into().unwrap());
+    let expo = i32::from_be_bytes(payload[48..52].try_into().unwrap());
+    let _ema_price = i64::from_be_bytes(payload[52..60].try_into().unwrap());
+    let publish_time = i64::from_be_bytes(payload[60..68].try_into().unwrap());
+
+    Ok((
+        feed_id,
+        PythPrice {
+            price,
+            confidence,
+            expo,
+            publish_time,
+        },
+    ))
+}
+
+/// Reads a cached Pyth price, rejecting it if older than `max_staleness`
+/// relative to `now`. Returns the price together with its confidence so callers
+/// can widen penalties when `confidence / price` is large.
+pub fn load_fresh_pyth_price(
+    deps: Deps,
+    feed_id: &[u8; 32],
+    now: u64,
+    max_staleness: u64,
+) -> StdResult<PythPrice> {
+    let price = read_pyth_price(deps.storage, feed_id)?;
+    let publish_time = price.publish_time as u64;
+    if now.saturating_sub(publish_time) > max_staleness {
+        return Err(StdError::generic_err(format!(
+            "pyth price is stale: published {}, now {}, max staleness {}",
+            publish_time, now, max_staleness
+        )));
+    }
+    Ok(price)
+}
+
+/// RegisterClusterId
+/// Allocates a deterministic cw1155 token-id for a cluster so its share token
+/// and staked LP positions live under the single `multitoken_contract` instead
+/// of a standalone cw20. The id is derived from the cluster address so it is
+/// stable across re-registration.
+pub fn register_cluster_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    cluster: String,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    if config.multitoken_contract.is_none() {
+        return Err(StdError::generic_err("multitoken mode is not enabled"));
+    }
+
+    let cluster_raw = deps.api.addr_canonicalize(&cluster)?;
+    let token_id = cluster_token_id(cluster_raw.as_slice());
+    store_cluster_token_id(deps.storage, &cluster_raw, &token_id)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "register_cluster_id"),
+        attr("cluster", cluster),
+        attr("token_id", token_id),
+    ]))
+}
+
+/// Deterministic cw1155 token-id for a cluster: the hex of the first 16 bytes
+/// of `keccak256(cluster_address)`.
+fn cluster_token_id(cluster_raw: &[u8]) -> String {
+    let digest = keccak256(cluster_raw);
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode the VAA payload into the cluster [`Params`] to admit. The bridge
+/// emits the wrapped-asset metadata as a JSON-serialized `Params` so the
+/// existing `create_cluster` flow can be reused verbatim on the far side.
+fn decode_foreign_asset_payload(payload: &[u8]) -> StdResult<Params> {
+    cosmwasm_std::from_slice(payload)
 }
 
 /// Whitelisting process
@@ -251,228 +702,178 @@ pub fn pass_command<S: Storage, A: Api, Q: Querier>(
 ///    2-3. Create terraswap pair through terraswap factory
 /// 3. Call `TerraswapCreationHook`
 ///    3-1. Register asset to staking contract
-pub fn create_cluster<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
+pub fn create_cluster(
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     params: Params,
-) -> HandleResult {
-    let config: Config = read_config(&deps.storage)?;
-    if config.owner != deps.api.canonical_address(&env.message.sender)? {
-        return Err(StdError::unauthorized());
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
     }
 
-    if read_params(&deps.storage).is_ok() {
+    if read_params(deps.storage).is_ok() {
         return Err(StdError::generic_err("A whitelist process is in progress"));
     }
 
-    store_params(&mut deps.storage, &params)?;
+    store_params(deps.storage, &params)?;
 
-    Ok(HandleResponse {
-        messages: vec![
+    // Instantiate the cluster and handle its address in `reply` so the flow is
+    // deterministic and free of the old init_hook re-entrancy.
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
             CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: None,
                 code_id: config.cluster_code_id,
-                send: vec![],
-                label: None,
-                msg: to_binary(&BasketInitMsg {
+                funds: vec![],
+                label: params.name.clone(),
+                msg: to_binary(&BasketInstantiateMsg {
                     name: params.name.clone(),
-                    owner: env.contract.address.clone(),
-                    assets: params.assets,
+                    owner: env.contract.address.to_string(),
+                    assets: params.assets.clone(),
                     oracle: params.oracle.clone(),
                     penalty: params.penalty,
                     basket_token: None,
-                    target: params.target,
-                    // TODO: Write separate init hook for basket
-                    init_hook: Some(InitHook {
-                        contract_addr: env.contract.address,
-                        msg: to_binary(&HandleMsg::TokenCreationHook {})?,
-                    }),
+                    target: params.target.clone(),
                 })?,
-            }), // CosmosMsg::Wasm(WasmMsg::Instantiate {
-                //     code_id: config.token_code_id,
-                //     send: vec![],
-                //     label: None,
-                //     msg: to_binary(&TokenInitMsg {
-                //         name: name.clone(),
-                //         symbol: symbol.to_string(),
-                //         decimals: 6u8,
-                //         initial_balances: vec![],
-                //         mint: Some(MinterResponse {
-                //             minter: ????//deps.api.human_address(&config.mint_contract)?,
-                //             cap: None,
-                //         }),
-                //         init_hook: Some(InitHook {
-                //             contract_addr: env.contract.address,
-                //             msg: to_binary(&HandleMsg::TokenCreationHook { oracle_feeder })?,
-                //         }),
-                //     })?
-        ],
-        log: vec![
-            log("action", "create_cluster"),
-            log("symbol", params.symbol.clone()),
-            log("name", params.name.clone()),
-        ],
-        data: None,
-    })
+            }),
+            CLUSTER_REPLY_ID,
+        ))
+        .add_attributes(vec![
+            attr("action", "create_cluster"),
+            attr("symbol", params.symbol),
+            attr("name", params.name),
+        ]))
 }
 
-/// TokenCreationHook
-/// 1. Initialize distribution info
-/// 2. Register asset and oracle feeder to oracle contract
-/// 3. Create terraswap pair through terraswap factory with `TerraswapCreationHook`
-pub fn token_creation_hook<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-) -> HandleResult {
-    let config: Config = read_config(&deps.storage)?;
-
-    // If the param is not exists, it means there is no cluster registration process in progress
-    let params: Params = match read_params(&deps.storage) {
-        Ok(v) => v,
-        Err(_) => {
-            return Err(StdError::generic_err(
-                "No cluster registration process in progress",
-            ))
-        }
-    };
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        CLUSTER_REPLY_ID => reply_cluster_instantiated(deps, env, msg),
+        CLUSTER_TOKEN_REPLY_ID => reply_token_instantiated(deps, env, msg),
+        _ => Err(StdError::generic_err("unknown reply id")),
+    }
+}
 
-    let cluster = env.message.sender;
+/// Reply for the cluster instantiation: extract the new cluster address from the
+/// `MsgInstantiateContractResponse`, remember it for the token reply, and
+/// instantiate the cluster-token cw20 whose address we learn in
+/// [`reply_token_instantiated`]. Ownership is handed to governance only at the
+/// very end of the token reply, after the factory has set the basket token.
+fn reply_cluster_instantiated(deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
+    let res = parse_reply_instantiate_data(msg)
+        .map_err(|e| StdError::generic_err(format!("failed to parse cluster reply: {}", e)))?;
+    let cluster = deps.api.addr_validate(&res.contract_address)?;
 
+    let config: Config = read_config(deps.storage)?;
+    let params: Params = read_params(deps.storage)?;
 
-    // Register asset to mint contract
-    // Register asset to oracle contract
-    // Create terraswap pair
+    // stash the cluster so the token reply can wire the two together
+    store_tmp_cluster(deps.storage, &deps.api.addr_canonicalize(cluster.as_str())?)?;
 
-    Ok(HandleResponse {
-        messages: vec![
-            // Instantiate token
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
             CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: None,
                 code_id: config.token_code_id,
-                send: vec![],
-                label: None,
-                msg: to_binary(&TokenInitMsg {
+                funds: vec![],
+                label: params.symbol.clone(),
+                msg: to_binary(&TokenInstantiateMsg {
                     name: params.name.clone(),
                     symbol: params.symbol.clone(),
                     decimals: 6u8,
                     initial_balances: vec![],
                     mint: Some(MinterResponse {
-                        minter: cluster.clone(), //deps.api.human_address(&config.mint_contract)?,
+                        minter: cluster.to_string(),
                         cap: None,
                     }),
-                    // Set Basket Token
-                    init_hook: Some(InitHook {
-                        contract_addr: env.contract.address.clone(),
-                        msg: to_binary(&HandleMsg::SetBasketTokenHook {
-                            cluster: cluster.clone(),
-                        })?,
-                    }),
-                })?,
-            }),
-            // Reset cluster token
-            CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: cluster.clone(),
-                send: vec![],
-                msg: to_binary(&BasketHandleMsg::_ResetOwner {
-                    owner: deps.api.human_address(&config.owner)?,
                 })?,
             }),
-        ],
-        log: vec![log("cluster_addr", cluster.as_str())],
-        data: None,
-    })
+            CLUSTER_TOKEN_REPLY_ID,
+        ))
+        .add_attribute("cluster_addr", cluster.to_string()))
 }
 
-/// Set Token Hook
-pub fn set_basket_token_hook<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    cluster: HumanAddr,
-) -> HandleResult {
-    let config: Config = read_config(&deps.storage)?;
-    let token = env.message.sender;
-
-    let token_raw = deps.api.canonical_address(&token)?;
-
-    // If the param is not exists, it means there is no cluster registration process in progress
-    let params: Params = match read_params(&deps.storage) {
-        Ok(v) => v,
-        Err(_) => {
-            return Err(StdError::generic_err(
-                "No cluster registration process in progress",
-            ))
-        }
-    };
+/// Reply for the cluster-token instantiation: extract the new token address,
+/// record its weight, point the cluster at it, and create the terraswap pair.
+/// This replaces the old `SetBasketTokenHook` callback.
+fn reply_token_instantiated(deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
+    let res = parse_reply_instantiate_data(msg)
+        .map_err(|e| StdError::generic_err(format!("failed to parse token reply: {}", e)))?;
+    let token = deps.api.addr_validate(&res.contract_address)?;
+    let token_raw = deps.api.addr_canonicalize(token.as_str())?;
 
-    // If weight is given as params, we use that or just use default
-    let weight = if let Some(weight) = params.weight {
-        weight
-    } else {
-        NORMAL_TOKEN_WEIGHT
-    };
+    let config: Config = read_config(deps.storage)?;
+    let params: Params = read_params(deps.storage)?;
+    let cluster = deps.api.addr_humanize(&read_tmp_cluster(deps.storage)?)?;
 
-    store_weight(&mut deps.storage, &token_raw, weight)?;
-    increase_total_weight(&mut deps.storage, weight)?;
+    // If weight is given as params, we use that or just use default
+    let weight = params.weight.unwrap_or(NORMAL_TOKEN_WEIGHT);
+    store_weight(deps.storage, &token_raw, weight)?;
+    increase_total_weight(deps.storage, weight)?;
 
     // Remove params == clear flag
-    remove_params(&mut deps.storage);
+    remove_params(deps.storage);
 
-    // Register asset to mint contract
-    // Create terraswap pair
-    Ok(HandleResponse {
-        messages: vec![
-            //Set cluster token
+    Ok(Response::new()
+        .add_messages(vec![
+            // Set cluster token
             CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: cluster.clone(),
-                send: vec![],
-                msg: to_binary(&BasketHandleMsg::_SetBasketToken {
-                    basket_token: token.clone(),
+                contract_addr: cluster.to_string(),
+                funds: vec![],
+                msg: to_binary(&BasketExecuteMsg::_SetBasketToken {
+                    basket_token: token.to_string(),
                 })?,
             }),
             CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.human_address(&config.terraswap_factory)?,
-                send: vec![],
-                msg: to_binary(&TerraswapFactoryHandleMsg::CreatePair {
+                contract_addr: deps.api.addr_humanize(&config.terraswap_factory)?.to_string(),
+                funds: vec![],
+                msg: to_binary(&TerraswapFactoryExecuteMsg::CreatePair {
                     asset_infos: [
                         AssetInfo::NativeToken {
                             denom: config.base_denom,
                         },
                         AssetInfo::Token {
-                            contract_addr: token.clone(),
+                            contract_addr: token.to_string(),
                         },
                     ],
-                    init_hook: Some(InitHook {
-                        msg: to_binary(&HandleMsg::TerraswapCreationHook {
-                            asset_token: token.clone(),
-                        })?,
-                        contract_addr: env.contract.address,
-                    }),
                 })?,
             }),
-        ],
-        log: vec![
-            log("action", "set_cluster_token"),
-            log("cluster", cluster.clone()),
-            log("token", token.clone()),
-        ],
-        data: None,
-    })
+            // Hand ownership to governance last, once the factory has finished
+            // wiring the cluster and token together.
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: cluster.to_string(),
+                funds: vec![],
+                msg: to_binary(&BasketExecuteMsg::_ResetOwner {
+                    owner: deps.api.addr_humanize(&config.owner)?.to_string(),
+                })?,
+            }),
+        ])
+        .add_attributes(vec![
+            attr("action", "set_cluster_token"),
+            attr("cluster", cluster.to_string()),
+            attr("token", token.to_string()),
+        ]))
 }
+
 /// 1. Register asset and liquidity(LP) token to staking contract
-pub fn terraswap_creation_hook<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    asset_token: HumanAddr,
-) -> HandleResult {
+pub fn terraswap_creation_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_token: String,
+) -> StdResult<Response> {
     // Now terraswap contract is already created,
     // and liquidty token also created
-    let config: Config = read_config(&deps.storage)?;
-    let asset_token_raw = deps.api.canonical_address(&asset_token)?;
-    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let config: Config = read_config(deps.storage)?;
+    let asset_token_raw = deps.api.addr_canonicalize(&asset_token)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
 
     if config.nebula_token == asset_token_raw {
-        store_weight(&mut deps.storage, &asset_token_raw, NEBULA_TOKEN_WEIGHT)?;
-        increase_total_weight(&mut deps.storage, NEBULA_TOKEN_WEIGHT)?;
+        store_weight(deps.storage, &asset_token_raw, NEBULA_TOKEN_WEIGHT)?;
+        increase_total_weight(deps.storage, NEBULA_TOKEN_WEIGHT)?;
     } else if config.terraswap_factory != sender_raw {
-        return Err(StdError::unauthorized());
+        return Err(StdError::generic_err("unauthorized"));
     }
 
     let asset_infos = [
@@ -485,249 +886,348 @@ pub fn terraswap_creation_hook<S: Storage, A: Api, Q: Querier>(
     ];
 
     // Load terraswap pair info
-    let pair_info: PairInfo = query_pair_info(
-        &deps,
-        &deps.api.human_address(&config.terraswap_factory)?,
-        &asset_infos,
-    )?;
+    let terraswap_factory = deps.api.addr_humanize(&config.terraswap_factory)?;
+    let pair_info: PairInfo = query_pair_info(&deps.querier, &terraswap_factory, &asset_infos)?;
 
     // Execute staking contract to register staking token of newly created asset
-    Ok(HandleResponse {
-        // messages: vec![],
-        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: deps.api.human_address(&config.staking_contract)?,
-            send: vec![],
-            msg: to_binary(&StakingHandleMsg::RegisterAsset {
-                asset_token,
-                staking_token: pair_info.liquidity_token,
-            })?,
-        })],
-        log: vec![],
-        data: None,
-    })
+    Ok(Response::new().add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&config.staking_contract)?.to_string(),
+        funds: vec![],
+        msg: to_binary(&StakingExecuteMsg::RegisterAsset {
+            asset_token,
+            staking_token: pair_info.liquidity_token,
+        })?,
+    })))
+}
+
+/// DecommissionCluster
+/// Retires a cluster: authorize via the registered oracle feeder, remove the
+/// cluster's weight from the distribution (using its actual stored weight so the
+/// total can never underflow), and freeze the cluster so it stops accepting
+/// mints and settles redemptions at `end_price`.
+pub fn decommission_cluster(
+    deps: DepsMut,
+    info: MessageInfo,
+    cluster_token: String,
+    end_price: Decimal,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let cluster_token_raw: CanonicalAddr = deps.api.addr_canonicalize(&cluster_token)?;
+    let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
+    let oracle_feeder: Addr = deps.api.addr_humanize(&load_oracle_feeder(
+        deps.as_ref(),
+        &oracle_contract,
+        &cluster_token_raw,
+    )?)?;
+
+    if oracle_feeder != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // decrease by the cluster's own weight, not a hardcoded default, so Nebula-
+    // or custom-weighted clusters settle the total weight correctly.
+    let weight = read_weight(deps.storage, &cluster_token_raw)?;
+    remove_weight(deps.storage, &cluster_token_raw);
+    decrease_total_weight(deps.storage, weight)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cluster_token.clone(),
+            funds: vec![],
+            msg: to_binary(&BasketExecuteMsg::Decommission { end_price })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "decommission_cluster"),
+            attr("cluster_token", cluster_token),
+            attr("end_price", end_price.to_string()),
+        ]))
+}
+
+/// MigrateCluster
+/// Decommission `from_cluster` at `end_price` and spin up a replacement,
+/// carrying over the old cluster's weight into the new cluster's params.
+pub fn migrate_cluster(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    symbol: String,
+    from_cluster: String,
+    end_price: Decimal,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let from_cluster_raw: CanonicalAddr = deps.api.addr_canonicalize(&from_cluster)?;
+    let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
+    let oracle_feeder: Addr = deps.api.addr_humanize(&load_oracle_feeder(
+        deps.as_ref(),
+        &oracle_contract,
+        &from_cluster_raw,
+    )?)?;
+
+    if oracle_feeder != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    if read_params(deps.storage).is_ok() {
+        return Err(StdError::generic_err("A whitelist process is in progress"));
+    }
+
+    let weight = read_weight(deps.storage, &from_cluster_raw)?;
+    remove_weight(deps.storage, &from_cluster_raw);
+    decrease_total_weight(deps.storage, weight)?;
+
+    // reuse the retired cluster's composition (assets, oracle, penalty, target)
+    // and carry over its weight into the replacement.
+    let params = Params {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        weight: Some(weight),
+        ..read_cluster_params(deps.storage, &from_cluster_raw)?
+    };
+    store_params(deps.storage, &params)?;
+
+    Ok(Response::new()
+        // freeze and settle the old cluster
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: from_cluster.clone(),
+            funds: vec![],
+            msg: to_binary(&BasketExecuteMsg::Decommission { end_price })?,
+        }))
+        // instantiate the replacement, continuing into the same reply flow as
+        // create_cluster (cluster reply -> token reply -> terraswap pair)
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: None,
+                code_id: config.cluster_code_id,
+                funds: vec![],
+                label: params.name.clone(),
+                msg: to_binary(&BasketInstantiateMsg {
+                    name: params.name.clone(),
+                    owner: env.contract.address.to_string(),
+                    assets: params.assets.clone(),
+                    oracle: params.oracle.clone(),
+                    penalty: params.penalty,
+                    basket_token: None,
+                    target: params.target.clone(),
+                })?,
+            }),
+            CLUSTER_REPLY_ID,
+        ))
+        .add_attributes(vec![
+            attr("action", "migrate_cluster"),
+            attr("from_cluster", from_cluster),
+            attr("name", name),
+            attr("symbol", symbol),
+            attr("end_price", end_price.to_string()),
+        ]))
+}
+
+/// Integrated emission of an [`EmissionCurve`] over the elapsed-time interval
+/// `[a, b)` (seconds since genesis). For an exponential curve the per-second
+/// rate at elapsed time `t` is `rate0 * (1/2)^(t/half_life)`.
+fn emission_between(curve: &EmissionCurve, a: u64, b: u64) -> StdResult<Uint128> {
+    if b <= a {
+        return Ok(Uint128::zero());
+    }
+    match curve {
+        EmissionCurve::Exponential {
+            genesis_amount_per_sec,
+            half_life,
+        } => {
+            if *half_life == 0 {
+                return Err(StdError::generic_err("half_life must be non-zero"));
+            }
+            let rate0 = *genesis_amount_per_sec;
+
+            // A single integration method is used for every interval length so
+            // that `distribute` and `query_distribution_amount` never disagree
+            // at a boundary: the closed-form integral of the exponential rate.
+            //   amount = rate0 * half_life/ln2 * (2^(-a/hl) - 2^(-b/hl))
+            let diff = half_pow(a, *half_life) - half_pow(b, *half_life);
+            // 1/ln2 ~= 1.442695
+            let coef = Decimal::from_ratio((*half_life as u128) * 1_442_695u128, 1_000_000u128);
+            Ok((coef * diff) * rate0)
+        }
+    }
+}
+
+/// Fixed-point `(1/2)^(num/den)`. The integer part is computed exactly with
+/// exponentiation by squaring; the fractional part uses a truncated Taylor
+/// expansion of `2^(-x) = exp(-x·ln2)` (terms through `x^5`, < 2e-4 error on
+/// `[0, 1)`), which keeps emission accounting smooth across sub-second rates.
+fn half_pow(num: u64, den: u64) -> Decimal {
+    let half = Decimal::from_ratio(1u128, 2u128);
+    let q = num / den;
+    let r = num % den;
+
+    let mut integer_part = Decimal::one();
+    let mut base = half;
+    let mut exp = q;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            integer_part = integer_part * base;
+        }
+        base = base * base;
+        exp >>= 1;
+    }
+
+    // (1/2)^(r/den) = sum_n (-x·ln2)^n / n!, x = r/den in [0, 1).
+    // Coefficients a_n = (ln2)^n / n!; accumulate the positive (even) and
+    // negative (odd) terms separately so every Decimal stays non-negative.
+    let x = Decimal::from_ratio(r as u128, den as u128);
+    let x2 = x * x;
+    let x3 = x2 * x;
+    let x4 = x2 * x2;
+    let x5 = x4 * x;
+    let a1 = Decimal::from_ratio(693_147u128, 1_000_000u128);
+    let a2 = Decimal::from_ratio(240_227u128, 1_000_000u128);
+    let a3 = Decimal::from_ratio(55_504u128, 1_000_000u128);
+    let a4 = Decimal::from_ratio(9_618u128, 1_000_000u128);
+    let a5 = Decimal::from_ratio(1_333u128, 1_000_000u128);
+    let positive = Decimal::one() + a2 * x2 + a4 * x4;
+    let negative = a1 * x + a3 * x3 + a5 * x5;
+    let fractional_part = positive - negative;
+    integer_part * fractional_part
 }
 
 /// Distribute
 /// Anyone can execute distribute operation to distribute
 /// nebula inflation rewards on the staking pool
-pub fn distribute<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-) -> HandleResult {
-    let last_distributed = read_last_distributed(&deps.storage)?;
-    if last_distributed + DISTRIBUTION_INTERVAL > env.block.time {
+pub fn distribute(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let now = env.block.time.seconds();
+    let last_distributed = read_last_distributed(deps.storage)?;
+    if last_distributed + DISTRIBUTION_INTERVAL > now {
         return Err(StdError::generic_err(
             "Cannot distribute nebula token before interval",
         ));
     }
 
-    let config: Config = read_config(&deps.storage)?;
-    let time_elapsed = env.block.time - config.genesis_time;
+    let config: Config = read_config(deps.storage)?;
+    let time_elapsed = now - config.genesis_time;
     let last_time_elapsed = last_distributed - config.genesis_time;
-    let mut target_distribution_amount: Uint128 = Uint128::zero();
-    for s in config.distribution_schedule.iter() {
-        if s.0 > time_elapsed || s.1 < last_time_elapsed {
-            continue;
-        }
 
-        // min(s.1, time_elapsed) - max(s.0, last_time_elapsed)
-        let time_duration =
-            std::cmp::min(s.1, time_elapsed) - std::cmp::max(s.0, last_time_elapsed);
+    // A configured emission curve overrides the piecewise-constant schedule.
+    let target_distribution_amount: Uint128 = if let Some(curve) = &config.emission_curve {
+        emission_between(curve, last_time_elapsed, time_elapsed)?
+    } else {
+        let mut amount: Uint128 = Uint128::zero();
+        for s in config.distribution_schedule.iter() {
+            if s.0 > time_elapsed || s.1 < last_time_elapsed {
+                continue;
+            }
 
-        let time_slot = s.1 - s.0;
-        let distribution_amount_per_sec: Decimal = Decimal::from_ratio(s.2, time_slot);
-        target_distribution_amount += distribution_amount_per_sec * Uint128(time_duration as u128);
-    }
+            // min(s.1, time_elapsed) - max(s.0, last_time_elapsed)
+            let time_duration =
+                std::cmp::min(s.1, time_elapsed) - std::cmp::max(s.0, last_time_elapsed);
 
-    let staking_contract = deps.api.human_address(&config.staking_contract)?;
-    let nebula_token = deps.api.human_address(&config.nebula_token)?;
+            let time_slot = s.1 - s.0;
+            let distribution_amount_per_sec: Decimal = Decimal::from_ratio(s.2, time_slot);
+            amount += distribution_amount_per_sec * Uint128::from(time_duration as u128);
+        }
+        amount
+    };
 
-    let total_weight: u32 = read_total_weight(&deps.storage)?;
+    let staking_contract = deps.api.addr_humanize(&config.staking_contract)?;
+    let nebula_token = deps.api.addr_humanize(&config.nebula_token)?;
+
+    let total_weight: u32 = read_total_weight(deps.storage)?;
     let mut distribution_amount: Uint128 = Uint128::zero();
-    let weights: Vec<(CanonicalAddr, u32)> = read_all_weight(&deps.storage)?;
-    let rewards: Vec<(HumanAddr, Uint128)> = weights
+    let weights: Vec<(CanonicalAddr, u32)> = read_all_weight(deps.storage)?;
+    let rewards: Vec<(String, Uint128)> = weights
         .iter()
         .map(|w| {
-            let amount =
-                target_distribution_amount * Decimal::from_ratio(w.1 as u128, total_weight as u128);
+            let amount = target_distribution_amount
+                * Decimal::from_ratio(w.1 as u128, total_weight as u128);
 
             if amount.is_zero() {
                 return Err(StdError::generic_err("cannot distribute zero amount"));
             }
 
             distribution_amount += amount;
-            Ok((deps.api.human_address(&w.0)?, amount))
+            Ok((deps.api.addr_humanize(&w.0)?.to_string(), amount))
         })
         .filter(|m| m.is_ok())
-        .collect::<StdResult<Vec<(HumanAddr, Uint128)>>>()?;
+        .collect::<StdResult<Vec<(String, Uint128)>>>()?;
 
     // store last distributed
-    store_last_distributed(&mut deps.storage, env.block.time)?;
-
-    // mint token to self and try send minted tokens to staking contract
-    Ok(HandleResponse {
-        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: nebula_token.clone(),
-            msg: to_binary(&Cw20HandleMsg::Send {
-                contract: staking_contract.clone(),
+    store_last_distributed(deps.storage, now)?;
+
+    // mint token to self and try send minted tokens to staking contract. In
+    // multitoken mode rewards settle against the cw1155 registry in one batch;
+    // otherwise fall back to the per-cw20 send.
+    let message = if let Some(multitoken_contract) = config.multitoken_contract {
+        let multitoken = deps.api.addr_humanize(&multitoken_contract)?;
+        // Only cluster tokens live in the cw1155 registry. Plain-cw20 weight
+        // holders such as the Nebula governance token have no registered
+        // token id, so they are skipped rather than aborting the batch. The
+        // hook payload is rebuilt against the surviving token ids.
+        let mut batch = vec![];
+        let mut token_rewards: Vec<(String, Uint128)> = vec![];
+        for (recipient, amount) in rewards.iter() {
+            let recipient_raw = deps.api.addr_canonicalize(recipient)?;
+            let token_id = match read_cluster_token_id(deps.storage, &recipient_raw) {
+                Ok(token_id) => token_id,
+                Err(_) => continue,
+            };
+            batch.push((token_id.clone(), *amount));
+            token_rewards.push((token_id, *amount));
+        }
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: multitoken.to_string(),
+            msg: to_binary(&Cw1155ExecuteMsg::BatchSendFrom {
+                from: env.contract.address.to_string(),
+                to: staking_contract.to_string(),
+                batch,
+                msg: Some(to_binary(&StakingCw20HookMsg::DepositReward {
+                    rewards: token_rewards,
+                })?),
+            })?,
+            funds: vec![],
+        })
+    } else {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: nebula_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: staking_contract.to_string(),
                 amount: distribution_amount,
-                msg: Some(to_binary(&StakingCw20HookMsg::DepositReward { rewards })?),
+                msg: to_binary(&StakingCw20HookMsg::DepositReward { rewards })?,
             })?,
-            send: vec![],
-        })],
-        log: vec![
-            log("action", "distribute"),
-            log("distribution_amount", distribution_amount.to_string()),
-        ],
-        data: None,
-    })
+            funds: vec![],
+        })
+    };
+
+    Ok(Response::new().add_message(message).add_attributes(vec![
+        attr("action", "distribute"),
+        attr("distribution_amount", distribution_amount.to_string()),
+    ]))
 }
 
-// pub fn revoke_asset<S: Storage, A: Api, Q: Querier>(
-//     deps: &mut Extern<S, A, Q>,
-//     env: Env,
-//     asset_token: HumanAddr,
-//     end_price: Decimal,
-// ) -> HandleResult {
-//     let config: Config = read_config(&deps.storage)?;
-//     let asset_token_raw: CanonicalAddr = deps.api.canonical_address(&asset_token)?;
-//     let oracle_feeder: HumanAddr = deps.api.human_address(&load_oracle_feeder(
-//         &deps,
-//         &deps.api.human_address(&config.oracle_contract)?,
-//         &asset_token_raw,
-//     )?)?;
-
-//     if oracle_feeder != env.message.sender {
-//         return Err(StdError::unauthorized());
-//     }
-
-//     remove_weight(&mut deps.storage, &asset_token_raw);
-//     decrease_total_weight(&mut deps.storage, NORMAL_TOKEN_WEIGHT)?;
-
-//     Ok(HandleResponse {
-//         messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
-//             contract_addr: deps.api.human_address(&config.mint_contract)?,
-//             send: vec![],
-//             msg: to_binary(&MintHandleMsg::RegisterMigration {
-//                 asset_token: asset_token.clone(),
-//                 end_price,
-//             })?,
-//         })],
-//         log: vec![
-//             log("action", "revoke_asset"),
-//             log("end_price", end_price.to_string()),
-//             log("asset_token", asset_token.to_string()),
-//         ],
-//         data: None,
-//     })
-// }
-
-// pub fn migrate_asset<S: Storage, A: Api, Q: Querier>(
-//     deps: &mut Extern<S, A, Q>,
-//     env: Env,
-//     name: String,
-//     symbol: String,
-//     asset_token: HumanAddr,
-//     end_price: Decimal,
-// ) -> HandleResult {
-//     let config: Config = read_config(&deps.storage)?;
-//     let asset_token_raw: CanonicalAddr = deps.api.canonical_address(&asset_token)?;
-//     let oracle_feeder: HumanAddr = deps.api.human_address(&load_oracle_feeder(
-//         &deps,
-//         &deps.api.human_address(&config.oracle_contract)?,
-//         &asset_token_raw,
-//     )?)?;
-
-//     if oracle_feeder != env.message.sender {
-//         return Err(StdError::unauthorized());
-//     }
-
-//     let weight = read_weight(&deps.storage, &asset_token_raw)?;
-//     remove_weight(&mut deps.storage, &asset_token_raw);
-//     decrease_total_weight(&mut deps.storage, NORMAL_TOKEN_WEIGHT)?;
-
-//     let mint_contract = deps.api.human_address(&config.mint_contract)?;
-//     let mint_config: (Decimal, Decimal, Option<Decimal>) =
-//         load_mint_asset_config(&deps, &mint_contract, &asset_token_raw)?;
-
-//     // check if the asset being migrated specifies a min CR after migration
-//     let min_collateral_ratio = if let Some(min_collateral_ratio_after_migration) = mint_config.2 {
-//         min_collateral_ratio_after_migration
-//     } else {
-//         mint_config.1
-//     };
-
-//     store_params(
-//         &mut deps.storage,
-//         &Params {
-//             auction_discount: mint_config.0,
-//             min_collateral_ratio,
-//             weight: Some(weight),
-//             mint_period: None,
-//             min_collateral_ratio_after_migration: None,
-//         },
-//     )?;
-
-//     Ok(HandleResponse {
-//         messages: vec![
-//             CosmosMsg::Wasm(WasmMsg::Execute {
-//                 contract_addr: mint_contract,
-//                 send: vec![],
-//                 msg: to_binary(&MintHandleMsg::RegisterMigration {
-//                     asset_token: asset_token.clone(),
-//                     end_price,
-//                 })?,
-//             }),
-//             CosmosMsg::Wasm(WasmMsg::Instantiate {
-//                 code_id: config.token_code_id,
-//                 send: vec![],
-//                 label: None,
-//                 msg: to_binary(&TokenInitMsg {
-//                     name,
-//                     symbol,
-//                     decimals: 6u8,
-//                     initial_balances: vec![],
-//                     mint: Some(MinterResponse {
-//                         minter: deps.api.human_address(&config.mint_contract)?,
-//                         cap: None,
-//                     }),
-//                     init_hook: Some(InitHook {
-//                         contract_addr: env.contract.address,
-//                         msg: to_binary(&HandleMsg::TokenCreationHook { oracle_feeder })?,
-//                     }),
-//                 })?,
-//             }),
-//         ],
-//         log: vec![
-//             log("action", "migration"),
-//             log("end_price", end_price.to_string()),
-//             log("asset_token", asset_token.to_string()),
-//         ],
-//         data: None,
-//     })
-// }
-
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        // QueryMsg::DistributionInfo {} => to_binary(&query_distribution_info(deps)?),
+        QueryMsg::DistributionInfo {} => to_binary(&query_distribution_info(deps)?),
+        QueryMsg::DistributionAmount { timestamp } => {
+            to_binary(&query_distribution_amount(deps, timestamp)?)
+        }
     }
 }
 
-pub fn query_config<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<ConfigResponse> {
-    let state = read_config(&deps.storage)?;
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = read_config(deps.storage)?;
     let resp = ConfigResponse {
-        owner: deps.api.human_address(&state.owner)?,
-        nebula_token: deps.api.human_address(&state.nebula_token)?,
-        oracle_contract: deps.api.human_address(&state.oracle_contract)?,
-        terraswap_factory: deps.api.human_address(&state.terraswap_factory)?,
-        staking_contract: deps.api.human_address(&state.staking_contract)?,
-        commission_collector: deps.api.human_address(&state.commission_collector)?,
+        owner: deps.api.addr_humanize(&state.owner)?.to_string(),
+        nebula_token: deps.api.addr_humanize(&state.nebula_token)?.to_string(),
+        oracle_contract: deps.api.addr_humanize(&state.oracle_contract)?.to_string(),
+        terraswap_factory: deps
+            .api
+            .addr_humanize(&state.terraswap_factory)?
+            .to_string(),
+        staking_contract: deps.api.addr_humanize(&state.staking_contract)?.to_string(),
+        commission_collector: deps
+            .api
+            .addr_humanize(&state.commission_collector)?
+            .to_string(),
         token_code_id: state.token_code_id,
         cluster_code_id: state.cluster_code_id,
         base_denom: state.base_denom,
@@ -738,33 +1238,117 @@ pub fn query_config<S: Storage, A: Api, Q: Querier>(
     Ok(resp)
 }
 
-// pub fn query_distribution_info<S: Storage, A: Api, Q: Querier>(
-//     deps: &Extern<S, A, Q>,
-// ) -> StdResult<DistributionInfoResponse> {
-//     let weights: Vec<(CanonicalAddr, u32)> = read_all_weight(&deps.storage)?;
-//     let last_distributed = read_last_distributed(&deps.storage)?;
-//     let resp = DistributionInfoResponse {
-//         last_distributed,
-//         weights: weights
-//             .iter()
-//             .map(|w| Ok((deps.api.human_address(&w.0)?, w.1)))
-//             .collect::<StdResult<Vec<(HumanAddr, u32)>>>()?,
-//     };
-
-//     Ok(resp)
-// }
-
-// pub fn migrate<S: Storage, A: Api, Q: Querier>(
-//     deps: &mut Extern<S, A, Q>,
-//     _env: Env,
-//     _msg: MigrateMsg,
-// ) -> MigrateResult {
-//     let weights = read_all_weight(&deps.storage)?;
-//     for (asset_token, weight) in weights.iter() {
-//         store_weight(&mut deps.storage, &asset_token, weight * 100)?;
-//     }
-
-//     let total_weight = read_total_weight(&deps.storage)?;
-//     store_total_weight(&mut deps.storage, total_weight * 100)?;
-//     Ok(MigrateResponse::default())
-// }
+pub fn query_distribution_info(deps: Deps) -> StdResult<DistributionInfoResponse> {
+    let weights: Vec<(CanonicalAddr, u32)> = read_all_weight(deps.storage)?;
+    let last_distributed = read_last_distributed(deps.storage)?;
+    let resp = DistributionInfoResponse {
+        last_distributed,
+        weights: weights
+            .iter()
+            .map(|w| Ok((deps.api.addr_humanize(&w.0)?, w.1)))
+            .collect::<StdResult<Vec<(Addr, u32)>>>()?,
+        total_weight: read_total_weight(deps.storage)?,
+    };
+
+    Ok(resp)
+}
+
+/// Previews the Nebula emission that would accrue between `last_distributed` and
+/// `timestamp` without mutating state, prorating each overlapping schedule
+/// window linearly and splitting the total by the stored weight ratios. Lets
+/// front-ends and the staking contract estimate rewards ahead of `distribute`.
+pub fn query_distribution_amount(
+    deps: Deps,
+    timestamp: u64,
+) -> StdResult<DistributionAmountResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let last_distributed = read_last_distributed(deps.storage)?;
+
+    if timestamp <= last_distributed {
+        return Ok(DistributionAmountResponse {
+            amount: Uint128::zero(),
+            rewards: vec![],
+        });
+    }
+
+    let time_elapsed = timestamp - config.genesis_time;
+    let last_time_elapsed = last_distributed - config.genesis_time;
+
+    let target_distribution_amount: Uint128 = if let Some(curve) = &config.emission_curve {
+        emission_between(curve, last_time_elapsed, time_elapsed)?
+    } else {
+        let mut amount = Uint128::zero();
+        for s in config.distribution_schedule.iter() {
+            if s.0 > time_elapsed || s.1 < last_time_elapsed {
+                continue;
+            }
+            let time_duration =
+                std::cmp::min(s.1, time_elapsed) - std::cmp::max(s.0, last_time_elapsed);
+            let time_slot = s.1 - s.0;
+            let distribution_amount_per_sec: Decimal = Decimal::from_ratio(s.2, time_slot);
+            amount += distribution_amount_per_sec * Uint128::from(time_duration as u128);
+        }
+        amount
+    };
+
+    let total_weight: u32 = read_total_weight(deps.storage)?;
+    let weights: Vec<(CanonicalAddr, u32)> = read_all_weight(deps.storage)?;
+    let rewards = weights
+        .iter()
+        .map(|w| {
+            let amount = target_distribution_amount
+                * Decimal::from_ratio(w.1 as u128, total_weight as u128);
+            Ok((deps.api.addr_humanize(&w.0)?, amount))
+        })
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+
+    Ok(DistributionAmountResponse {
+        amount: target_distribution_amount,
+        rewards,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let weights = read_all_weight(deps.storage)?;
+    for (asset_token, weight) in weights.iter() {
+        store_weight(deps.storage, asset_token, weight * 100)?;
+    }
+
+    let total_weight = read_total_weight(deps.storage)?;
+    store_total_weight(deps.storage, total_weight * 100)?;
+    Ok(Response::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{read_total_weight, store_total_weight, store_weight};
+    use cosmwasm_std::testing::mock_dependencies;
+
+    // A cluster registered with a non-default weight must decrease the total by
+    // exactly that weight when it is decommissioned, never by the hardcoded
+    // NORMAL_TOKEN_WEIGHT, so the total can never underflow.
+    #[test]
+    fn decommission_decreases_by_actual_weight() {
+        let mut deps = mock_dependencies(&[]);
+        let cluster = deps.api.addr_canonicalize("cluster0").unwrap();
+
+        store_total_weight(deps.as_mut().storage, NEBULA_TOKEN_WEIGHT).unwrap();
+        store_weight(deps.as_mut().storage, &cluster, NEBULA_TOKEN_WEIGHT).unwrap();
+
+        let weight = read_weight(deps.as_ref().storage, &cluster).unwrap();
+        remove_weight(deps.as_mut().storage, &cluster);
+        decrease_total_weight(deps.as_mut().storage, weight).unwrap();
+
+        assert_eq!(read_total_weight(deps.as_ref().storage).unwrap(), 0u32);
+    }
+
+    // Decreasing by more than the stored total must error rather than wrap.
+    #[test]
+    fn decrease_total_weight_underflow_errors() {
+        let mut deps = mock_dependencies(&[]);
+        store_total_weight(deps.as_mut().storage, NORMAL_TOKEN_WEIGHT).unwrap();
+        assert!(decrease_total_weight(deps.as_mut().storage, NEBULA_TOKEN_WEIGHT).is_err());
+    }
+}
@@ -3,15 +3,23 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, from_slice, to_binary, Api, CanonicalAddr, Coin, ContractResult, Decimal, Empty,
-    OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError, SystemResult, WasmQuery,
+    from_binary, from_slice, to_binary, AllDelegationsResponse, AllValidatorsResponse, Api,
+    BalanceResponse, BankQuery, BondedDenomResponse, CanonicalAddr, Coin, ContractResult,
+    CustomQuery, Decimal, DelegationResponse, Empty, FullDelegation, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, StakingQuery, SystemError, SystemResult, Validator,
+    ValidatorResponse, WasmQuery,
 };
 use cosmwasm_storage::to_length_prefixed;
 
-use crate::querier::MintAssetConfig;
+use crate::querier::{MintAssetConfig, NebulaQuery};
 use std::collections::HashMap;
 use terraswap::asset::{AssetInfo, PairInfo};
 
+/// Callback invoked for every `QueryRequest::Custom(C)` the contract issues.
+/// Tests install one via [`WasmMockQuerier::with_custom_handler`] to stub
+/// chain-native queries (e.g. token-factory balances) that have no CW20 form.
+type CustomHandler<C> = Box<dyn Fn(&C) -> QuerierResult>;
+
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
 /// this uses our CustomQuerier.
 pub fn mock_dependencies(
@@ -28,11 +36,58 @@ pub fn mock_dependencies(
     }
 }
 
-pub struct WasmMockQuerier {
-    base: MockQuerier<Empty>,
+pub struct WasmMockQuerier<C: CustomQuery = NebulaQuery> {
+    base: MockQuerier<C>,
     terraswap_factory_querier: TerraswapFactoryQuerier,
     oracle_querier: OracleQuerier,
     mint_querier: MintQuerier,
+    staking_querier: StakingQuerier,
+    bank_querier: BankQuerier,
+    custom_handler: CustomHandler<C>,
+}
+
+#[derive(Clone, Default)]
+pub struct StakingQuerier {
+    denom: String,
+    validators: Vec<Validator>,
+    delegations: HashMap<(String, String), FullDelegation>,
+}
+
+impl StakingQuerier {
+    pub fn new(
+        denom: &str,
+        validators: &[Validator],
+        delegations: &[FullDelegation],
+    ) -> Self {
+        StakingQuerier {
+            denom: denom.to_string(),
+            validators: validators.to_vec(),
+            delegations: delegations
+                .iter()
+                .map(|d| {
+                    (
+                        (d.delegator.to_string(), d.validator.to_string()),
+                        d.clone(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BankQuerier {
+    balances: HashMap<String, Vec<Coin>>,
+}
+
+impl BankQuerier {
+    pub fn new(balances: &[(&String, &[Coin])]) -> Self {
+        let mut map: HashMap<String, Vec<Coin>> = HashMap::new();
+        for (addr, coins) in balances.iter() {
+            map.insert(addr.to_string(), coins.to_vec());
+        }
+        BankQuerier { balances: map }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -66,10 +121,9 @@ pub struct MintQuerier {
     configs: HashMap<String, (Decimal, Decimal, Option<Decimal>)>,
 }
 
-impl Querier for WasmMockQuerier {
+impl<C: CustomQuery + serde::de::DeserializeOwned> Querier for WasmMockQuerier<C> {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
-        // MockQuerier doesn't support Custom, so we ignore it completely here
-        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+        let request: QueryRequest<C> = match from_slice(bin_request) {
             Ok(v) => v,
             Err(e) => {
                 return SystemResult::Err(SystemError::InvalidRequest {
@@ -88,9 +142,10 @@ pub enum QueryMsg {
     Pair { asset_infos: [AssetInfo; 2] },
 }
 
-impl WasmMockQuerier {
-    pub fn execute_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+impl<C: CustomQuery + serde::de::DeserializeOwned> WasmMockQuerier<C> {
+    pub fn execute_query(&self, request: &QueryRequest<C>) -> QuerierResult {
         match &request {
+            QueryRequest::Custom(custom_query) => (*self.custom_handler)(custom_query),
             QueryRequest::Wasm(WasmQuery::Smart {
                 contract_addr: _,
                 msg,
@@ -187,23 +242,132 @@ impl WasmMockQuerier {
                     panic!("DO NOT ENTER HERE")
                 }
             }
+            QueryRequest::Staking(staking_query) => match staking_query {
+                StakingQuery::BondedDenom {} => {
+                    SystemResult::Ok(ContractResult::from(to_binary(&BondedDenomResponse {
+                        denom: self.staking_querier.denom.clone(),
+                    })))
+                }
+                StakingQuery::AllValidators {} => {
+                    SystemResult::Ok(ContractResult::from(to_binary(&AllValidatorsResponse {
+                        validators: self.staking_querier.validators.clone(),
+                    })))
+                }
+                StakingQuery::Validator { address } => {
+                    let validator = self
+                        .staking_querier
+                        .validators
+                        .iter()
+                        .find(|v| &v.address == address)
+                        .cloned();
+                    SystemResult::Ok(ContractResult::from(to_binary(&ValidatorResponse {
+                        validator,
+                    })))
+                }
+                StakingQuery::AllDelegations { delegator } => {
+                    let delegations = self
+                        .staking_querier
+                        .delegations
+                        .values()
+                        .filter(|d| &d.delegator.to_string() == delegator)
+                        .map(|d| cosmwasm_std::Delegation {
+                            delegator: d.delegator.clone(),
+                            validator: d.validator.clone(),
+                            amount: d.amount.clone(),
+                        })
+                        .collect();
+                    SystemResult::Ok(ContractResult::from(to_binary(&AllDelegationsResponse {
+                        delegations,
+                    })))
+                }
+                StakingQuery::Delegation {
+                    delegator,
+                    validator,
+                } => {
+                    let delegation = self
+                        .staking_querier
+                        .delegations
+                        .get(&(delegator.to_string(), validator.to_string()))
+                        .cloned();
+                    SystemResult::Ok(ContractResult::from(to_binary(&DelegationResponse {
+                        delegation,
+                    })))
+                }
+                _ => self.base.handle_query(request),
+            },
+            // addresses not configured via `with_balance` fall back to the base
+            // querier, which holds the `contract_balance` given to mock_dependencies
+            QueryRequest::Bank(bank_query) => match bank_query {
+                BankQuery::Balance { address, denom } => match self.bank_querier.balances.get(address) {
+                    Some(coins) => {
+                        let amount = coins
+                            .iter()
+                            .find(|c| &c.denom == denom)
+                            .map(|c| c.amount)
+                            .unwrap_or_default();
+                        SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                            amount: Coin {
+                                denom: denom.clone(),
+                                amount,
+                            },
+                        })))
+                    }
+                    None => self.base.handle_query(request),
+                },
+                BankQuery::AllBalances { address } => match self.bank_querier.balances.get(address) {
+                    Some(amount) => SystemResult::Ok(ContractResult::from(to_binary(
+                        &cosmwasm_std::AllBalanceResponse {
+                            amount: amount.clone(),
+                        },
+                    ))),
+                    None => self.base.handle_query(request),
+                },
+                _ => self.base.handle_query(request),
+            },
             _ => self.base.handle_query(request),
         }
     }
 }
 
-impl WasmMockQuerier {
-    pub fn new(base: MockQuerier<Empty>) -> Self {
+impl<C: CustomQuery + serde::de::DeserializeOwned> WasmMockQuerier<C> {
+    pub fn new(base: MockQuerier<C>) -> Self {
         WasmMockQuerier {
             base,
             terraswap_factory_querier: TerraswapFactoryQuerier::default(),
             mint_querier: MintQuerier::default(),
             oracle_querier: OracleQuerier::default(),
+            staking_querier: StakingQuerier::default(),
+            bank_querier: BankQuerier::default(),
+            custom_handler: Box::new(|_| {
+                SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "custom".to_string(),
+                })
+            }),
         }
     }
 
+    // install a handler for chain-native custom queries (token-factory balances, etc.)
+    pub fn with_custom_handler<H: Fn(&C) -> QuerierResult + 'static>(&mut self, handler: H) {
+        self.custom_handler = Box::new(handler);
+    }
+
     // configure the terraswap pair
     pub fn with_terraswap_pairs(&mut self, pairs: &[(&String, &String)]) {
         self.terraswap_factory_querier = TerraswapFactoryQuerier::new(pairs);
     }
+
+    // configure the staking module (bonded denom, validator set and delegations)
+    pub fn with_staking(
+        &mut self,
+        denom: &str,
+        validators: &[Validator],
+        delegations: &[FullDelegation],
+    ) {
+        self.staking_querier = StakingQuerier::new(denom, validators, delegations);
+    }
+
+    // configure native coin balances for a given address
+    pub fn with_balance(&mut self, balances: &[(&String, &[Coin])]) {
+        self.bank_querier = BankQuerier::new(balances);
+    }
 }
@@ -2,20 +2,27 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-    attr, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, QueryRequest, Response,
-    StdError, StdResult, Uint128, WasmQuery,
+    attr, to_binary, Binary, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo, QueryRequest,
+    Response, StdError, StdResult, Uint128, Uint256, WasmQuery,
 };
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, PriceResponse, QueryMsg};
-use crate::state::{read_config, store_config, Config};
+use std::convert::TryFrom;
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, PriceResponse, QueryMsg, TwapPriceResponse};
+use crate::state::{
+    read_config, read_observations, read_pool_pair, read_pyth_feed, store_config,
+    store_observation, Config, PriceObservation,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use tefi_oracle::hub::{
     HubQueryMsg as TeFiOracleQueryMsg, PriceResponse as TeFiOraclePriceResponse,
 };
 use terra_cosmwasm::{ExchangeRatesResponse, TerraQuerier};
 use terraswap::asset::AssetInfo;
-
-const DECIMAL_FRACTIONAL: Uint128 = Uint128::new(1_000_000_000u128);
+use terraswap::pair::{PoolResponse, QueryMsg as TerraswapPairQueryMsg};
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -28,6 +35,8 @@ pub fn instantiate(
         owner: msg.owner.clone(),
         oracle_addr: msg.oracle_addr,
         base_denom: msg.base_denom,
+        // maximum tolerated price age, in seconds; 0 disables the check
+        max_price_age: msg.max_price_age,
     };
 
     store_config(deps.storage, &cfg)?;
@@ -40,7 +49,7 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> StdResult<Response> {
@@ -49,7 +58,9 @@ pub fn execute(
             owner,
             oracle_addr,
             base_denom,
-        } => update_config(deps, info, owner, oracle_addr, base_denom),
+            max_price_age,
+        } => update_config(deps, info, owner, oracle_addr, base_denom, max_price_age),
+        ExecuteMsg::RecordPrice { asset } => record_price(deps, env, asset),
     }
 }
 
@@ -59,6 +70,7 @@ pub fn update_config(
     owner: Option<String>,
     oracle_addr: Option<String>,
     base_denom: Option<String>,
+    max_price_age: Option<u64>,
 ) -> StdResult<Response> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -78,55 +90,263 @@ pub fn update_config(
         config.base_denom = base_denom;
     }
 
+    if let Some(max_price_age) = max_price_age {
+        config.max_price_age = max_price_age;
+    }
+
     store_config(deps.storage, &config)?;
     Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Price {
             base_asset,
             quote_asset,
-        } => to_binary(&query_price(deps, base_asset, quote_asset)?),
+        } => to_binary(&query_price(deps, env, base_asset, quote_asset)?),
+        QueryMsg::TwapPrice { asset, window } => {
+            to_binary(&query_twap_price(deps, env, asset, window)?)
+        }
     }
 }
 
 fn query_price(
     deps: Deps,
+    env: Env,
     base_asset: AssetInfo,
     quote_asset: AssetInfo,
 ) -> StdResult<PriceResponse> {
-    let (price_base, last_updated_base) = query_asset_price(deps, base_asset)?;
-    let (price_quote, last_updated_quote) = query_asset_price(deps, quote_asset)?;
+    let config: Config = read_config(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    let decimals_base = asset_decimals(deps, &base_asset)?;
+    let decimals_quote = asset_decimals(deps, &quote_asset)?;
 
-    let rate = Decimal::from_ratio(
-        price_base * DECIMAL_FRACTIONAL,
-        price_quote * DECIMAL_FRACTIONAL,
-    );
+    let (price_base, last_updated_base) = query_asset_price(deps, &env, base_asset)?;
+    let (price_quote, last_updated_quote) = query_asset_price(deps, &env, quote_asset)?;
+
+    // reject prices older than the configured bound (0 disables the check)
+    assert_fresh(&config, now, last_updated_base)?;
+    assert_fresh(&config, now, last_updated_quote)?;
+
+    if price_quote.is_zero() {
+        return Err(StdError::generic_err("quote price is zero"));
+    }
+
+    // perform the division in 256-bit space to avoid the overflow and precision
+    // loss of multiplying a Decimal by DECIMAL_FRACTIONAL with high-value feeds.
+    let base256 = Decimal256::from(price_base);
+    let quote256 = Decimal256::from(price_quote);
+    let mut rate256 = base256.checked_div(quote256).map_err(|e| {
+        StdError::generic_err(format!("price division overflow: {}", e))
+    })?;
+
+    // the two feeds may be quoted in assets with different on-chain decimals;
+    // shift the rate by 10^(decimals_quote - decimals_base) so it reflects one
+    // whole unit of the base asset priced in whole units of the quote asset.
+    rate256 = scale_for_decimals(rate256, decimals_base, decimals_quote);
+
+    // narrow back to Decimal, erroring if the 256-bit result does not fit
+    let rate = Decimal::try_from(rate256)
+        .map_err(|_| StdError::generic_err("price rate does not fit in Decimal"))?;
 
     Ok(PriceResponse {
         rate,
         last_updated_base,
         last_updated_quote,
+        decimals_base,
+        decimals_quote,
     })
 }
 
-fn query_asset_price(deps: Deps, asset: AssetInfo) -> StdResult<(Decimal, u64)> {
+/// Shift a base/quote rate by `10^(decimals_quote - decimals_base)` so it is
+/// expressed per whole unit of the base asset in whole units of the quote
+/// asset. A quote with more decimals than the base scales the rate up; a base
+/// with more decimals scales it down.
+pub(crate) fn scale_for_decimals(
+    rate: Decimal256,
+    decimals_base: u8,
+    decimals_quote: u8,
+) -> Decimal256 {
+    if decimals_quote > decimals_base {
+        let shift = Uint256::from(10u128).pow((decimals_quote - decimals_base) as u32);
+        rate * Decimal256::from_ratio(shift, Uint256::from(1u128))
+    } else if decimals_base > decimals_quote {
+        let shift = Uint256::from(10u128).pow((decimals_base - decimals_quote) as u32);
+        rate * Decimal256::from_ratio(Uint256::from(1u128), shift)
+    } else {
+        rate
+    }
+}
+
+/// Errors if `last_updated` is older than `config.max_price_age` relative to
+/// `now`. A `max_price_age` of 0 disables the check.
+fn assert_fresh(config: &Config, now: u64, last_updated: u64) -> StdResult<()> {
+    if config.max_price_age == 0 {
+        return Ok(());
+    }
+    if now.saturating_sub(last_updated) > config.max_price_age {
+        return Err(StdError::generic_err(format!(
+            "price is stale: last updated {}, now {}, max age {}",
+            last_updated, now, config.max_price_age
+        )));
+    }
+    Ok(())
+}
+
+fn query_asset_price(deps: Deps, env: &Env, asset: AssetInfo) -> StdResult<(Decimal, u64)> {
     let config: Config = read_config(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    // collect a quote from every backend configured for this asset. A source
+    // that errors or returns a stale timestamp is dropped rather than aborting
+    // the whole query, so one failing or manipulated feed cannot dominate.
+    let mut quotes: Vec<(Decimal, u64)> = vec![];
 
-    match asset {
-        AssetInfo::NativeToken { denom } => query_native_price(deps, denom, &config),
-        AssetInfo::Token { contract_addr } => query_cw20_price(deps, contract_addr, &config),
+    if let Some(price_feed_id) = read_pyth_feed(deps.storage, &asset)? {
+        if let Some(pyth_addr) = config.pyth_addr.clone() {
+            push_if_fresh(&mut quotes, &config, now, query_pyth_price(deps, pyth_addr, price_feed_id));
+        }
+    }
+
+    match &asset {
+        AssetInfo::NativeToken { denom } => {
+            push_if_fresh(
+                &mut quotes,
+                &config,
+                now,
+                query_native_price(deps, env, denom.clone(), &config),
+            );
+        }
+        AssetInfo::Token { contract_addr } => {
+            push_if_fresh(
+                &mut quotes,
+                &config,
+                now,
+                query_cw20_price(deps, contract_addr.clone(), &config),
+            );
+        }
+    }
+
+    // last resort: when no oracle source responded and the integrator has opted
+    // in, derive a spot price from a registered terraswap pool. A raw AMM spot
+    // price is manipulable, so it is gated behind `allow_pool_fallback`.
+    if quotes.is_empty() && config.allow_pool_fallback {
+        if let Some(pair_addr) = read_pool_pair(deps.storage, &asset)? {
+            push_if_fresh(&mut quotes, &config, now, query_pool_price(deps, pair_addr, &asset, now));
+        }
+    }
+
+    if (quotes.len() as u64) < config.min_valid_sources {
+        return Err(StdError::generic_err(format!(
+            "not enough valid price sources: got {}, need {}",
+            quotes.len(),
+            config.min_valid_sources
+        )));
+    }
+
+    Ok((median_price(&quotes), quotes.iter().map(|(_, t)| *t).max().unwrap()))
+}
+
+/// Pushes a source's result onto `quotes`, dropping it if the query failed or
+/// the price is older than the configured freshness bound.
+fn push_if_fresh(
+    quotes: &mut Vec<(Decimal, u64)>,
+    config: &Config,
+    now: u64,
+    result: StdResult<(Decimal, u64)>,
+) {
+    if let Ok((price, last_updated)) = result {
+        if assert_fresh(config, now, last_updated).is_ok() {
+            quotes.push((price, last_updated));
+        }
     }
 }
 
-fn query_native_price(deps: Deps, denom: String, config: &Config) -> StdResult<(Decimal, u64)> {
+/// Median of the collected prices. For an even number of quotes the two middle
+/// values are averaged. `quotes` must be non-empty.
+fn median_price(quotes: &[(Decimal, u64)]) -> Decimal {
+    let mut prices: Vec<Decimal> = quotes.iter().map(|(p, _)| *p).collect();
+    prices.sort();
+    let n = prices.len();
+    if n % 2 == 1 {
+        prices[n / 2]
+    } else {
+        (prices[n / 2 - 1] + prices[n / 2]) * Decimal::from_ratio(1u128, 2u128)
+    }
+}
+
+/// Query message sent to the Pyth contract. Only the price-feed variant is used
+/// here; the id is the 32-byte feed identifier registered for the asset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum PythQueryMsg {
+    PriceFeed { id: Binary },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct PythPriceFeedResponse {
+    price_feed: PythPriceFeed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct PythPriceFeed {
+    /// The latest price, expressed as `price * 10^expo`.
+    price: i64,
+    /// Signed power-of-ten exponent applied to `price`.
+    expo: i32,
+    /// Unix timestamp (seconds) the price was published at.
+    publish_time: u64,
+}
+
+/// Prices an asset from a Pyth price feed. Pyth reports an integer `price` and a
+/// signed `expo`; the real value is `price * 10^expo`, which we normalize into a
+/// non-negative [`Decimal`]. Returns the `(price, publish_time)` tuple the rest
+/// of the oracle expects.
+fn query_pyth_price(
+    deps: Deps,
+    pyth_addr: String,
+    price_feed_id: Binary,
+) -> StdResult<(Decimal, u64)> {
+    let res: PythPriceFeedResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: pyth_addr,
+            msg: to_binary(&PythQueryMsg::PriceFeed { id: price_feed_id })?,
+        }))?;
+
+    let feed = res.price_feed;
+    if feed.price < 0 {
+        return Err(StdError::generic_err("pyth reported a negative price"));
+    }
+    let price = Uint128::from(feed.price as u128);
+
+    // scale the integer mantissa by 10^expo into a Decimal
+    let price = if feed.expo >= 0 {
+        let scale = Uint128::from(10u128.pow(feed.expo as u32));
+        Decimal::from_ratio(price * scale, 1u128)
+    } else {
+        let scale = Uint128::from(10u128.pow((-feed.expo) as u32));
+        Decimal::from_ratio(price, scale)
+    };
+
+    Ok((price, feed.publish_time))
+}
+
+fn query_native_price(
+    deps: Deps,
+    env: &Env,
+    denom: String,
+    config: &Config,
+) -> StdResult<(Decimal, u64)> {
     let terra_querier = TerraQuerier::new(&deps.querier);
     let res: ExchangeRatesResponse =
         terra_querier.query_exchange_rates(denom, vec![config.base_denom.clone()])?;
 
-    Ok((res.exchange_rates[0].exchange_rate, u64::MAX))
+    // the exchange-rate querier has no notion of publish time; stamp it with the
+    // current block time so native feeds are subject to the same staleness check
+    // as the oracle-backed ones.
+    Ok((res.exchange_rates[0].exchange_rate, env.block.time.seconds()))
 }
 
 fn query_cw20_price(
@@ -146,3 +366,141 @@ fn query_cw20_price(
 
     Ok((res.rate, res.last_updated))
 }
+
+/// On-chain decimals of an asset: the cw20 `TokenInfo { decimals }` for tokens,
+/// and the configured `native_decimals` (default 6) for native denoms.
+fn asset_decimals(deps: Deps, info: &AssetInfo) -> StdResult<u8> {
+    match info {
+        AssetInfo::Token { contract_addr } => {
+            let res: cw20::TokenInfoResponse =
+                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                    contract_addr: contract_addr.clone(),
+                    msg: to_binary(&cw20::Cw20QueryMsg::TokenInfo {})?,
+                }))?;
+            Ok(res.decimals)
+        }
+        AssetInfo::NativeToken { .. } => {
+            let config = read_config(deps.storage)?;
+            Ok(config.native_decimals)
+        }
+    }
+}
+
+/// Derives the spot price of `target` from a terraswap pair using the
+/// constant-product reserves: `price = reserve_other / reserve_target`, adjusted
+/// for the differing decimals of the two sides so the result is expressed per
+/// whole unit. The pair has no publish time, so the price is stamped with `now`.
+fn query_pool_price(
+    deps: Deps,
+    pair_addr: String,
+    target: &AssetInfo,
+    now: u64,
+) -> StdResult<(Decimal, u64)> {
+    let pool: PoolResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: pair_addr,
+        msg: to_binary(&TerraswapPairQueryMsg::Pool {})?,
+    }))?;
+
+    let target_asset = pool
+        .assets
+        .iter()
+        .find(|a| &a.info == target)
+        .ok_or_else(|| StdError::generic_err("target asset not found in pool"))?;
+    let other_asset = pool
+        .assets
+        .iter()
+        .find(|a| &a.info != target)
+        .ok_or_else(|| StdError::generic_err("pair asset not found in pool"))?;
+
+    if target_asset.amount.is_zero() {
+        return Err(StdError::generic_err("empty target reserve in pool"));
+    }
+
+    let dec_target = asset_decimals(deps, &target_asset.info)?;
+    let dec_other = asset_decimals(deps, &other_asset.info)?;
+
+    // reserve_other / reserve_target, then shift by the decimal difference so the
+    // price is quoted per whole unit of the target.
+    let mut price = Decimal::from_ratio(other_asset.amount, target_asset.amount);
+    if dec_target > dec_other {
+        price = price * Decimal::from_ratio(10u128.pow((dec_target - dec_other) as u32), 1u128);
+    } else if dec_other > dec_target {
+        price = price * Decimal::from_ratio(1u128, 10u128.pow((dec_other - dec_target) as u32));
+    }
+
+    Ok((price, now))
+}
+
+/// Records the current spot price of `asset` into its observation ring buffer,
+/// feeding the time-weighted average computed by [`query_twap_price`]. Anyone
+/// may keep the buffer warm by periodically calling this.
+pub fn record_price(deps: DepsMut, env: Env, asset: AssetInfo) -> StdResult<Response> {
+    let (price, _last_updated) = query_asset_price(deps.as_ref(), &env, asset.clone())?;
+    let observation = PriceObservation {
+        timestamp: env.block.time.seconds(),
+        price,
+    };
+    store_observation(deps.storage, &asset, observation)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "record_price"),
+        attr("price", price.to_string()),
+    ]))
+}
+
+/// Time-weighted average price of `asset` over the trailing `window` seconds,
+/// integrated over the stored observations. Resists single-block manipulation
+/// that the spot `Price` query is exposed to.
+fn query_twap_price(
+    deps: Deps,
+    env: Env,
+    asset: AssetInfo,
+    window: u64,
+) -> StdResult<TwapPriceResponse> {
+    let now = env.block.time.seconds();
+    let cutoff = now.saturating_sub(window);
+
+    let mut observations = read_observations(deps.storage, &asset)?;
+    observations.retain(|o| o.timestamp >= cutoff);
+    observations.sort_by_key(|o| o.timestamp);
+
+    if observations.is_empty() {
+        return Err(StdError::generic_err(
+            "no price observations within the requested window",
+        ));
+    }
+
+    // trapezoidal integration of price over time, each segment weighted by its
+    // duration; the final observation is carried to `now`.
+    let mut weighted = Decimal::zero();
+    let mut total_secs: u64 = 0;
+    for pair in observations.windows(2) {
+        let dt = pair[1].timestamp - pair[0].timestamp;
+        if dt == 0 {
+            continue;
+        }
+        let avg = (pair[0].price + pair[1].price) * Decimal::from_ratio(1u128, 2u128);
+        weighted = weighted + avg * Decimal::from_ratio(dt, 1u128);
+        total_secs += dt;
+    }
+
+    // carry the last observation forward to the query time
+    let last = observations.last().unwrap();
+    let tail = now.saturating_sub(last.timestamp);
+    if tail > 0 {
+        weighted = weighted + last.price * Decimal::from_ratio(tail, 1u128);
+        total_secs += tail;
+    }
+
+    let twap = if total_secs == 0 {
+        last.price
+    } else {
+        weighted * Decimal::from_ratio(1u128, total_secs)
+    };
+
+    Ok(TwapPriceResponse {
+        rate: twap,
+        window,
+        num_observations: observations.len() as u64,
+    })
+}
@@ -1,9 +1,9 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, query, scale_for_decimals};
 use crate::state::{read_config, Config};
 use crate::testing::mock_querier::mock_dependencies;
 use astroport::asset::AssetInfo;
 use cosmwasm_std::testing::{mock_env, mock_info};
-use cosmwasm_std::{from_binary, Addr, Decimal, StdError};
+use cosmwasm_std::{from_binary, Addr, Decimal, Decimal256, StdError};
 use nebula_protocol::oracle::{ExecuteMsg, InstantiateMsg, PriceResponse, QueryMsg};
 use std::str::FromStr;
 
@@ -149,3 +149,32 @@ fn query_price() {
         Decimal::from_str("0.015052281774035657").unwrap()
     );
 }
+
+#[test]
+fn scale_rate_for_differing_decimals() {
+    // starting from a unit base/quote rate, the shift is exactly
+    // 10^(decimals_quote - decimals_base) in both magnitude and direction.
+    let one = Decimal256::one();
+
+    // equal decimals leave the rate untouched
+    assert_eq!(scale_for_decimals(one, 6, 6), one);
+
+    // a quote with more decimals than the base scales the rate up
+    assert_eq!(
+        scale_for_decimals(one, 6, 8),
+        Decimal256::from_str("100").unwrap()
+    );
+
+    // a base with more decimals than the quote scales the rate down
+    assert_eq!(
+        scale_for_decimals(one, 8, 6),
+        Decimal256::from_str("0.01").unwrap()
+    );
+
+    // the shift composes with a non-unit rate
+    let rate = Decimal256::from_str("2.5").unwrap();
+    assert_eq!(
+        scale_for_decimals(rate, 6, 9),
+        Decimal256::from_str("2500").unwrap()
+    );
+}
@@ -0,0 +1,29 @@
+/// Error types for basket-math
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+/// Arithmetic failure raised by the `checked_*` FPDecimal operators instead of
+/// the panicking `assert!`/overflowing `*` used by their unchecked counterparts.
+#[derive(Error, Debug, PartialEq)]
+pub enum OverflowError {
+    #[error("FPDecimal overflow in {operation}")]
+    Overflow { operation: String },
+
+    #[error("FPDecimal divide by zero")]
+    DivideByZero,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    FPDecimal(#[from] OverflowError),
+}
+
+impl From<OverflowError> for StdError {
+    fn from(err: OverflowError) -> Self {
+        StdError::generic_err(err.to_string())
+    }
+}
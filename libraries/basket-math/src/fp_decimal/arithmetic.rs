@@ -1,4 +1,5 @@
 /// Arithmetic operators for FPDecimal
+use crate::error::OverflowError;
 use crate::fp_decimal::{FPDecimal, U256};
 use std::ops;
 
@@ -74,10 +75,170 @@ impl FPDecimal {
         }
     }
 
+    /// Square root via Newton-Raphson on the fixed-point representation.
+    /// `x` must be non-negative; a negative argument is a programming error.
+    pub fn _sqrt(x: FPDecimal) -> FPDecimal {
+        assert!(x.sign == 1, "square root of a negative number");
+        if x.num == U256::zero() {
+            return FPDecimal {num: U256::zero(), sign: 1};
+        }
+        let two = FPDecimal {num: FPDecimal::ONE.num * U256([2, 0, 0, 0]), sign: 1};
+        // seed with the average of x and one so the iteration converges for x both below and above one
+        let mut g = FPDecimal::_div(FPDecimal::_add(x, FPDecimal::ONE), two);
+        for _ in 0..40 {
+            let g_next = FPDecimal::_div(FPDecimal::_add(g, FPDecimal::_div(x, g)), two);
+            let diff = FPDecimal::_sub(g_next, g);
+            g = g_next;
+            if diff.num < U256::one() {
+                break;
+            }
+        }
+        g
+    }
+
+    pub fn sqrt(&self) -> FPDecimal {
+        FPDecimal::_sqrt(*self)
+    }
+
+    /// Natural logarithm, obtained by solving exp(y) = x with Newton's method.
+    /// `x` must be strictly positive.
+    pub fn _ln(x: FPDecimal) -> FPDecimal {
+        assert!(x.sign == 1, "logarithm of a negative number");
+        assert!(x.num != U256::zero(), "logarithm of zero");
+        let ln2 = FPDecimal {num: U256([693147180559945309, 0, 0, 0]), sign: 1};
+        // seed y0 from the bit-length of the integer part times ln(2)
+        let int_part: U256 = FPDecimal::_int(x).num / FPDecimal::ONE.num;
+        let mut y = FPDecimal::_mul(FPDecimal::from(int_part.bits() as i128), ln2);
+        for _ in 0..40 {
+            let neg_y = FPDecimal {num: y.num, sign: 1 - y.sign};
+            // y_{n+1} = y_n + x * exp(-y_n) - 1
+            let y_next = FPDecimal::_sub(
+                FPDecimal::_add(y, FPDecimal::_mul(x, FPDecimal::_exp(neg_y))),
+                FPDecimal::ONE,
+            );
+            let diff = FPDecimal::_sub(y_next, y);
+            y = y_next;
+            if diff.num < U256::one() {
+                break;
+            }
+        }
+        y
+    }
+
+    pub fn ln(&self) -> FPDecimal {
+        FPDecimal::_ln(*self)
+    }
+
+    /// General power `base^exp` for `base > 0`. Integer exponents short-circuit
+    /// to repeated multiplication to preserve exactness.
+    pub fn _pow(base: FPDecimal, exp: FPDecimal) -> FPDecimal {
+        assert!(base.sign == 1, "power of a non-positive base");
+        if FPDecimal::_fraction(exp).num == U256::zero() {
+            let n: U256 = FPDecimal::_int(exp).num / FPDecimal::ONE.num;
+            let mut result = FPDecimal::ONE;
+            let mut i = U256::zero();
+            while i < n {
+                result = FPDecimal::_mul(result, base);
+                i = i + U256::one();
+            }
+            if exp.sign == 0 {
+                return FPDecimal::reciprocal(result);
+            }
+            return result;
+        }
+        FPDecimal::_exp(FPDecimal::_mul(exp, FPDecimal::_ln(base)))
+    }
+
+    pub fn pow(&self, exp: FPDecimal) -> FPDecimal {
+        FPDecimal::_pow(*self, exp)
+    }
+
     pub fn abs(&self) -> FPDecimal {
         FPDecimal { num: self.num, sign: 1i8}
     }
 
+    /// Overflow-safe addition. Returns `OverflowError::Overflow` instead of
+    /// panicking when the summed magnitude exceeds 256 bits.
+    pub fn checked_add(x: FPDecimal, y: FPDecimal) -> Result<FPDecimal, OverflowError> {
+        if x.sign == y.sign {
+            let (num, overflow) = x.num.overflowing_add(y.num);
+            if overflow {
+                return Err(OverflowError::Overflow {operation: "add".to_string()});
+            }
+            return Ok(FPDecimal {num, sign: x.sign});
+        }
+        // opposite signs collapse to a subtraction of magnitudes, which cannot overflow
+        Ok(FPDecimal::_add(x, y))
+    }
+
+    pub fn checked_sub(x: FPDecimal, y: FPDecimal) -> Result<FPDecimal, OverflowError> {
+        let neg_y = FPDecimal {num: y.num, sign: 1 - y.sign};
+        FPDecimal::checked_add(x, neg_y)
+    }
+
+    /// Overflow-safe multiplication mirroring `_mul` but guarding every
+    /// intermediate `U256` product against wrap-around.
+    pub fn checked_mul(x: FPDecimal, y: FPDecimal) -> Result<FPDecimal, OverflowError> {
+        let mut sign = 1;
+        if x.sign != y.sign {
+            sign = 0;
+        }
+        let x1: U256 = FPDecimal::_int(x).num / FPDecimal::ONE.num;
+        let mut x2: U256 = FPDecimal::_fraction(x).num;
+        let y1: U256 = FPDecimal::_int(y).num / FPDecimal::ONE.num;
+        let mut y2: U256 = FPDecimal::_fraction(y).num;
+
+        let overflow = || OverflowError::Overflow {operation: "mul".to_string()};
+
+        let (x1y1, o) = x1.overflowing_mul(y1);
+        if o {
+            return Err(overflow());
+        }
+        let (dec_x1y1, o) = x1y1.overflowing_mul(FPDecimal::ONE.num);
+        if o {
+            return Err(overflow());
+        }
+        let (x2y1, o) = x2.overflowing_mul(y1);
+        if o {
+            return Err(overflow());
+        }
+        let (x1y2, o) = x1.overflowing_mul(y2);
+        if o {
+            return Err(overflow());
+        }
+        x2 = x2 / FPDecimal::MUL_PRECISION.num;
+        y2 = y2 / FPDecimal::MUL_PRECISION.num;
+        let (x2y2, o) = x2.overflowing_mul(y2);
+        if o {
+            return Err(overflow());
+        }
+
+        let mut result = dec_x1y1;
+        for term in [x2y1, x1y2, x2y2] {
+            let (sum, o) = result.overflowing_add(term);
+            if o {
+                return Err(overflow());
+            }
+            result = sum;
+        }
+        Ok(FPDecimal {num: result, sign})
+    }
+
+    pub fn checked_div(x: FPDecimal, y: FPDecimal) -> Result<FPDecimal, OverflowError> {
+        if y.num == U256::zero() {
+            return Err(OverflowError::DivideByZero);
+        }
+        if y == FPDecimal::ONE {
+            return Ok(x);
+        }
+        let (one_sq, o) = FPDecimal::ONE.num.overflowing_mul(FPDecimal::ONE.num);
+        if o {
+            return Err(OverflowError::Overflow {operation: "div".to_string()});
+        }
+        let reciprocal = FPDecimal {num: one_sq / y.num, sign: y.sign};
+        FPDecimal::checked_mul(x, reciprocal)
+    }
+
     pub fn convertTou128(num:U256) -> u128 {
         let mut array: [u8; 16] = [0;16];
         for i in 0..16 {
@@ -219,4 +380,73 @@ mod tests {
         let five = FPDecimal {num: U256([5, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
         assert_eq!(neg_five.abs(), five);
     }
+
+    #[test]
+    fn test_sqrt() {
+        let four = FPDecimal {num: U256([4, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let two = FPDecimal {num: U256([2, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let diff = FPDecimal::_sub(FPDecimal::_sqrt(four), two);
+        assert!(diff.num < FPDecimal::ONE.num / U256([1000000, 0, 0, 0]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sqrt_negative() {
+        let neg_four = FPDecimal {num: U256([4, 0, 0, 0]) * FPDecimal::ONE.num, sign: 0};
+        FPDecimal::_sqrt(neg_four);
+    }
+
+    #[test]
+    fn test_ln_exp_roundtrip() {
+        let e = FPDecimal::_exp(FPDecimal::ONE);
+        let diff = FPDecimal::_sub(FPDecimal::_ln(e), FPDecimal::ONE);
+        assert!(diff.num < FPDecimal::ONE.num / U256([1000000, 0, 0, 0]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ln_zero() {
+        FPDecimal::_ln(FPDecimal {num: U256::zero(), sign: 1});
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let five = FPDecimal {num: U256([5, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let three = FPDecimal {num: U256([3, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let eight = FPDecimal {num: U256([8, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        assert_eq!(FPDecimal::checked_add(five, three), Ok(eight));
+    }
+
+    #[test]
+    fn test_checked_add_overflow() {
+        let max = FPDecimal {num: U256::max_value(), sign: 1};
+        let one = FPDecimal::ONE;
+        assert!(FPDecimal::checked_add(max, one).is_err());
+    }
+
+    #[test]
+    fn test_checked_div() {
+        let hundred = FPDecimal {num: U256([100, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let five = FPDecimal {num: U256([5, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let twenty = FPDecimal {num: U256([20, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        assert_eq!(FPDecimal::checked_div(hundred, five), Ok(twenty));
+    }
+
+    #[test]
+    fn test_checked_div_by_zero() {
+        let five = FPDecimal {num: U256([5, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let zero = FPDecimal {num: U256::zero(), sign: 1};
+        assert_eq!(
+            FPDecimal::checked_div(five, zero),
+            Err(crate::error::OverflowError::DivideByZero)
+        );
+    }
+
+    #[test]
+    fn test_pow_integer_exponent() {
+        let two = FPDecimal {num: U256([2, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let three = FPDecimal {num: U256([3, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        let eight = FPDecimal {num: U256([8, 0, 0, 0]) * FPDecimal::ONE.num, sign: 1};
+        assert_eq!(FPDecimal::_pow(two, three), eight);
+    }
 }
\ No newline at end of file
@@ -31,4 +31,38 @@ impl FPDecimal {
     pub fn tanh(&self) -> FPDecimal {
         FPDecimal::_tanh(*self)
     }
+
+    /// asinh(x) = ln(x + sqrt(x^2 + 1)), defined for all x and odd in x.
+    pub fn _asinh(x: FPDecimal) -> FPDecimal {
+        let radicand = FPDecimal::_add(FPDecimal::_mul(x, x), FPDecimal::ONE);
+        FPDecimal::_ln(FPDecimal::_add(x, FPDecimal::_sqrt(radicand)))
+    }
+
+    pub fn asinh(&self) -> FPDecimal {
+        FPDecimal::_asinh(*self)
+    }
+
+    /// acosh(x) = ln(x + sqrt(x^2 - 1)), requiring x >= 1.
+    pub fn _acosh(x: FPDecimal) -> FPDecimal {
+        assert!(x.sign == 1 && x.num >= FPDecimal::ONE.num, "acosh requires x >= 1");
+        let radicand = FPDecimal::_sub(FPDecimal::_mul(x, x), FPDecimal::ONE);
+        FPDecimal::_ln(FPDecimal::_add(x, FPDecimal::_sqrt(radicand)))
+    }
+
+    pub fn acosh(&self) -> FPDecimal {
+        FPDecimal::_acosh(*self)
+    }
+
+    /// atanh(x) = 1/2 * ln((1 + x) / (1 - x)), requiring |x| < 1 and odd in x.
+    pub fn _atanh(x: FPDecimal) -> FPDecimal {
+        assert!(x.num < FPDecimal::ONE.num, "atanh requires |x| < 1");
+        let two = FPDecimal {num: FPDecimal::ONE.num * U256([2, 0, 0, 0]), sign: 1};
+        let numerator = FPDecimal::_add(FPDecimal::ONE, x);
+        let denominator = FPDecimal::_sub(FPDecimal::ONE, x);
+        FPDecimal::_div(FPDecimal::_ln(FPDecimal::_div(numerator, denominator)), two)
+    }
+
+    pub fn atanh(&self) -> FPDecimal {
+        FPDecimal::_atanh(*self)
+    }
 }